@@ -0,0 +1,184 @@
+use chrono::{DateTime, Local};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::app::{App, ChatMessage, Screen};
+use crate::net::NetCommand;
+
+pub struct AdminArg {
+    pub name: &'static str,
+    pub required: bool,
+}
+
+pub struct AdminCommand {
+    pub name: &'static str,
+    pub args: &'static [AdminArg],
+    pub destructive: bool,
+}
+
+const fn arg(name: &'static str) -> AdminArg {
+    AdminArg { name, required: true }
+}
+
+const fn opt(name: &'static str) -> AdminArg {
+    AdminArg { name, required: false }
+}
+
+pub const ADMIN_COMMANDS: &[AdminCommand] = &[
+    AdminCommand { name: "invite create", args: &[opt("uses"), opt("expires")], destructive: false },
+    AdminCommand { name: "invite list", args: &[], destructive: false },
+    AdminCommand { name: "invite revoke", args: &[arg("code")], destructive: true },
+    AdminCommand { name: "user list", args: &[], destructive: false },
+    AdminCommand { name: "user kick", args: &[arg("username"), opt("reason")], destructive: true },
+    AdminCommand { name: "user ban", args: &[arg("username"), opt("reason")], destructive: true },
+    AdminCommand { name: "user unban", args: &[arg("username")], destructive: false },
+    AdminCommand { name: "key revoke", args: &[arg("fingerprint")], destructive: true },
+];
+
+pub struct AdminResult {
+    pub req_id: Option<u64>,
+    pub action: String,
+    pub data: Value,
+    pub received: DateTime<Local>,
+}
+
+#[derive(Default)]
+pub struct AdminConsole {
+    pub cursor: usize,
+    pub fields: Vec<String>,
+    pub field: Option<usize>,
+    pub confirm: bool,
+    pub error: Option<String>,
+    pub next_req_id: u64,
+    pub pending: HashMap<u64, String>,
+    pub results: Vec<AdminResult>,
+    pub viewing: usize,
+}
+
+impl AdminConsole {
+    pub fn command(&self) -> &'static AdminCommand {
+        &ADMIN_COMMANDS[self.cursor.min(ADMIN_COMMANDS.len() - 1)]
+    }
+
+    pub fn select(&mut self, idx: usize) {
+        self.cursor = idx.min(ADMIN_COMMANDS.len() - 1);
+        self.fields = vec![String::new(); self.command().args.len()];
+        self.field = None;
+        self.confirm = false;
+        self.error = None;
+    }
+
+    fn pending_label(&self, action: &str, req_id: Option<&str>) -> String {
+        req_id.and_then(|id| id.parse::<u64>().ok())
+            .and_then(|id| self.pending.get(&id).cloned())
+            .unwrap_or_else(|| action.to_string())
+    }
+
+    /// Files a response under the request it answers.
+    pub fn record(&mut self, action: String, data: Value, req_id: Option<String>) {
+        let req_id = req_id.and_then(|id| id.parse::<u64>().ok());
+        let request = req_id.and_then(|id| self.pending.remove(&id));
+        self.results.push(AdminResult {
+            req_id,
+            action: request.unwrap_or(action),
+            data,
+            received: Local::now(),
+        });
+        self.viewing = 0;
+    }
+
+    /// Records a response that arrived while this session was in the
+    /// background, returning the chat line that announces it.
+    pub fn record_in_background(&mut self, action: String, data: Value, req_id: Option<String>) -> ChatMessage {
+        let notice = admin_notice(&self.pending_label(&action, req_id.as_deref()), &data);
+        self.record(action, data, req_id);
+        notice
+    }
+
+    /// The command line as it would be typed after `/admin`.
+    pub fn preview(&self) -> String {
+        let mut line = self.command().name.to_string();
+        for value in self.fields.iter().filter(|v| !v.is_empty()) {
+            line.push(' ');
+            line.push_str(value);
+        }
+        line
+    }
+}
+
+fn admin_notice(label: &str, data: &Value) -> ChatMessage {
+    let text = match data {
+        Value::String(s) => format!("[{label}] {s}"),
+        _ => format!("[{label}] response received · /admin to view"),
+    };
+    ChatMessage {
+        from: "ADMIN".into(),
+        text,
+        timestamp: Local::now(),
+        is_system: true,
+        is_admin: true,
+        ..Default::default()
+    }
+}
+
+impl App {
+    pub fn open_admin(&mut self, command: Option<&str>, args: &[&str]) {
+        let idx = command
+            .and_then(|name| ADMIN_COMMANDS.iter().position(|c| c.name == name))
+            .unwrap_or(self.admin.cursor);
+        self.admin.select(idx);
+        // The last field takes the rest of the words, so a reason can contain spaces.
+        let last = self.admin.fields.len().saturating_sub(1);
+        for (i, slot) in self.admin.fields.iter_mut().enumerate() {
+            let words = if i == last { args.get(i..) } else { args.get(i..=i) };
+            *slot = words.unwrap_or_default().join(" ");
+        }
+        self.screen = Screen::Admin;
+    }
+
+    /// Validates the argument form and either asks for confirmation or sends the command.
+    pub fn submit_admin(&mut self) {
+        let cmd = self.admin.command();
+        let missing = cmd.args.iter()
+            .zip(&self.admin.fields)
+            .find(|(a, v)| a.required && v.trim().is_empty());
+        if let Some((a, _)) = missing {
+            self.admin.error = Some(format!("{} is required", a.name));
+            return;
+        }
+        self.admin.error = None;
+        if cmd.destructive && !self.admin.confirm {
+            self.admin.confirm = true;
+            return;
+        }
+        self.admin.confirm = false;
+        self.send_admin();
+    }
+
+    fn send_admin(&mut self) {
+        let cmd = self.admin.command();
+        let args = cmd.args.iter()
+            .zip(&self.admin.fields)
+            .filter(|(_, v)| !v.trim().is_empty())
+            .map(|(a, v)| (a.name.to_string(), Value::String(v.trim().to_string())))
+            .collect();
+        let action = cmd.name.to_string();
+
+        self.admin.next_req_id += 1;
+        let req_id = self.admin.next_req_id;
+        if self.send_net(NetCommand::Admin { req_id, action, args }) {
+            self.admin.pending.insert(req_id, self.admin.preview());
+            self.admin.field = None;
+        } else {
+            self.admin.error = Some("Not connected".into());
+        }
+    }
+
+    pub fn on_admin_response(&mut self, action: String, data: Value, req_id: Option<String>) {
+        let notice = admin_notice(&self.admin.pending_label(&action, req_id.as_deref()), &data);
+        if self.screen != Screen::Admin {
+            self.push_message(notice);
+        }
+        self.admin.record(action, data, req_id);
+    }
+}
@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use base64::Engine;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::app::{App, Screen};
+use crate::crypto;
+
+impl App {
+    /// Saves the key typed or pasted on the connect form, either a raw base64
+    /// seed or an exported backup. Returns false if connecting should wait,
+    /// e.g. for the backup passphrase or because the import failed.
+    pub fn import_manual_key(&mut self, key_path: &Path) -> bool {
+        let text = Zeroizing::new(self.connect_form.manual_key.trim().to_string());
+        if text.is_empty() {
+            return true;
+        }
+
+        let key = if crypto::is_backup(&text) {
+            let Some(passphrase) = self.connect_form.backup_passphrase.take().map(Zeroizing::new) else {
+                self.connect_form.backup_passphrase = Some(String::new());
+                self.connect_form.backup_error = None;
+                return false;
+            };
+            match crypto::import_backup(&text, &passphrase) {
+                Ok(key) => key,
+                Err(e) => {
+                    self.connect_form.backup_passphrase = Some(String::new());
+                    self.connect_form.backup_error = Some(format!("{e:#}"));
+                    return false;
+                }
+            }
+        } else {
+            let Ok(seed) = base64::engine::general_purpose::STANDARD.decode(text.as_str()).map(Zeroizing::new) else {
+                self.screen = Screen::Error("Invalid manual key format (expected Base64 or a key backup)".into());
+                self.clear_key_entry();
+                return false;
+            };
+            let Ok(seed) = <[u8; 32]>::try_from(seed.as_slice()).map(Zeroizing::new) else {
+                self.screen = Screen::Error("Invalid manual key length (expected 32 bytes)".into());
+                self.clear_key_entry();
+                return false;
+            };
+            ed25519_dalek::SigningKey::from_bytes(&seed)
+        };
+        self.connect_form.backup_error = None;
+
+        let pubkey = crypto::pubkey_b64(&key);
+        if key_path.exists() {
+            match crypto::load_or_generate(&key_path.to_path_buf()) {
+                Ok((existing, _)) if crypto::pubkey_b64(&existing) != pubkey => {
+                    self.screen = Screen::Error(format!(
+                        "{} already holds a different key. Wipe the local identity first to replace it.",
+                        key_path.display()
+                    ));
+                    self.clear_key_entry();
+                    return false;
+                }
+                _ => {}
+            }
+        }
+        if let Err(e) = crypto::save_key(key_path, &key) {
+            self.screen = Screen::Error(format!("Could not save imported key to {}: {e:#}", key_path.display()));
+            self.clear_key_entry();
+            return false;
+        }
+
+        self.clear_key_entry();
+        self.status_msg = Some(format!(
+            "Imported key {}",
+            crypto::fingerprint(&pubkey).unwrap_or_default()
+        ));
+        true
+    }
+
+    /// Wipes the key and backup passphrase typed on the connect form.
+    pub fn clear_key_entry(&mut self) {
+        self.connect_form.manual_key.zeroize();
+        if let Some(mut pass) = self.connect_form.backup_passphrase.take() {
+            pass.zeroize();
+        }
+        self.connect_form.backup_error = None;
+    }
+}
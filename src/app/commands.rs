@@ -0,0 +1,382 @@
+use crate::app::{App, PresenceState, Screen};
+use crate::app::admin::ADMIN_COMMANDS;
+use crate::config::{ClockStyle, QuietHours};
+use crate::crypto;
+use crate::net::NetCommand;
+
+impl App {
+    /// Handles client-side slash commands. Returns false when `text` should go to the server.
+    pub fn handle_command(&mut self, text: &str) -> bool {
+        let (cmd, arg) = match text.split_once(' ') {
+            Some((c, a)) => (c, a.trim()),
+            None => (text, ""),
+        };
+
+        match cmd {
+            "/mute" => self.cmd_mute(arg, true),
+            "/unmute" => self.cmd_mute(arg, false),
+            "/notify" => self.cmd_notify(arg),
+            "/quiet" => self.cmd_quiet(arg),
+            "/volume" => self.cmd_volume(arg),
+            "/sound" => self.cmd_sound(arg),
+            "/timefmt" => self.cmd_timefmt(arg),
+            "/edit" => self.cmd_edit(arg),
+            "/delete" => self.cmd_delete(),
+            "/react" => self.cmd_react(arg),
+            "/typing" => self.cmd_typing(arg),
+            "/away" => self.cmd_presence(PresenceState::Away, arg),
+            "/busy" => self.cmd_presence(PresenceState::Busy, arg),
+            "/back" => self.cmd_presence(PresenceState::Online, ""),
+            "/status" => self.cmd_status(arg),
+            "/joins" => self.cmd_joins(arg),
+            "/dm" | "/msg" => self.cmd_dm(arg),
+            "/admin" => self.cmd_admin(arg),
+            "/connect" => self.screen = Screen::Connect,
+            "/close" => self.close_active_session(),
+            "/keys" => self.cmd_keys(arg),
+            "/ignore" => self.cmd_ignore(arg, true),
+            "/unignore" => self.cmd_ignore(arg, false),
+            "/filters" => {
+                self.filters_form = Default::default();
+                self.screen = Screen::Filters;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn cmd_mute(&mut self, user: &str, mute: bool) {
+        if user.is_empty() {
+            self.notif_prefs.muted = mute;
+            self.save_profile_prefs();
+            if mute {
+                self.push_system_msg("Notifications muted. Type /unmute to re-enable.");
+            } else {
+                self.push_system_msg("Notifications unmuted.");
+            }
+            return;
+        }
+
+        let senders = &mut self.notif_prefs.muted_senders;
+        senders.retain(|s| s != user);
+        if mute {
+            senders.push(user.to_string());
+        }
+        self.save_profile_prefs();
+        let verb = if mute { "muted" } else { "unmuted" };
+        self.push_system_msg(&format!("Notifications from {user} {verb}."));
+    }
+
+    fn cmd_notify(&mut self, arg: &str) {
+        let mut parts = arg.split_whitespace();
+        let (kind, state) = (parts.next(), parts.next());
+
+        let enabled = match state {
+            Some("on") => true,
+            Some("off") => false,
+            _ => {
+                let p = &self.notif_prefs;
+                let on_off = |b: bool| if b { "on" } else { "off" };
+                let quiet = p.quiet_hours.map(|q| q.to_string()).unwrap_or_else(|| "off".into());
+                let muted = if p.muted_senders.is_empty() {
+                    "none".to_string()
+                } else {
+                    p.muted_senders.join(", ")
+                };
+                let summary = format!(
+                    "sound: {} · desktop: {} · title: {} · quiet: {} · muted users: {}",
+                    on_off(p.sound), on_off(p.desktop), on_off(p.title_counter), quiet, muted,
+                );
+                self.push_system_msg(&summary);
+                self.push_system_msg("Usage: /notify <sound|desktop|title> <on|off>");
+                return;
+            }
+        };
+
+        let slot = match kind {
+            Some("sound") => &mut self.notif_prefs.sound,
+            Some("desktop") => &mut self.notif_prefs.desktop,
+            Some("title") => &mut self.notif_prefs.title_counter,
+            _ => {
+                self.push_system_msg("Usage: /notify <sound|desktop|title> <on|off>");
+                return;
+            }
+        };
+        *slot = enabled;
+        self.save_profile_prefs();
+        self.push_system_msg(&format!(
+            "{} notifications {}.",
+            kind.unwrap_or_default(),
+            if enabled { "enabled" } else { "disabled" },
+        ));
+    }
+
+    fn cmd_quiet(&mut self, arg: &str) {
+        if arg == "off" {
+            self.notif_prefs.quiet_hours = None;
+            self.save_profile_prefs();
+            self.push_system_msg("Quiet hours disabled.");
+            return;
+        }
+        match QuietHours::parse(arg) {
+            Some(q) => {
+                self.notif_prefs.quiet_hours = Some(q);
+                self.save_profile_prefs();
+                self.push_system_msg(&format!("Quiet hours set to {q}."));
+            }
+            None => self.push_system_msg("Usage: /quiet HH:MM-HH:MM | /quiet off"),
+        }
+    }
+
+    fn cmd_volume(&mut self, arg: &str) {
+        let Ok(pct) = arg.trim_end_matches('%').parse::<u32>() else {
+            let pct = (self.config.sounds.volume * 100.0).round();
+            self.push_system_msg(&format!("Volume: {pct}%. Usage: /volume <0-200>"));
+            return;
+        };
+        self.config.sounds.volume = pct.min(200) as f32 / 100.0;
+        self.apply_sound_prefs();
+        self.push_system_msg(&format!("Volume set to {}%.", pct.min(200)));
+    }
+
+    fn cmd_sound(&mut self, arg: &str) {
+        let (kind, path) = match arg.split_once(' ') {
+            Some((k, p)) => (k, p.trim()),
+            None => (arg, ""),
+        };
+        let sounds = &mut self.config.sounds;
+        let slot = match kind {
+            "message" => &mut sounds.message,
+            "mention" => &mut sounds.mention,
+            "dm" => &mut sounds.dm,
+            "join" => &mut sounds.join,
+            _ => {
+                self.push_system_msg("Usage: /sound <message|mention|dm|join> <file|default>");
+                return;
+            }
+        };
+
+        if path.is_empty() || path == "default" {
+            *slot = None;
+            self.apply_sound_prefs();
+            self.push_system_msg(&format!("{kind} sound reset to default."));
+            return;
+        }
+        let path = std::path::PathBuf::from(path);
+        if !path.is_file() {
+            self.push_system_msg(&format!("Sound file not found: {}", path.display()));
+            return;
+        }
+        if let Err(e) = crate::notify::read_sound(&path) {
+            self.push_system_msg(&format!("Cannot play {}: {e}", path.display()));
+            return;
+        }
+        let shown = path.display().to_string();
+        *slot = Some(path);
+        self.apply_sound_prefs();
+        self.push_system_msg(&format!("{kind} sound set to {shown}."));
+    }
+
+    fn apply_sound_prefs(&mut self) {
+        self.audio.configure(self.config.sounds.clone());
+        let _ = self.config.save();
+    }
+
+    fn cmd_timefmt(&mut self, arg: &str) {
+        let fmt = &mut self.config.timestamp;
+        match arg {
+            "24h" => fmt.clock = ClockStyle::H24,
+            "12h" => fmt.clock = ClockStyle::H12,
+            "seconds" => fmt.seconds = true,
+            "noseconds" => fmt.seconds = false,
+            "relative" => fmt.relative = true,
+            "absolute" => fmt.relative = false,
+            _ => {
+                self.push_system_msg("Usage: /timefmt <24h|12h|seconds|noseconds|relative|absolute>");
+                return;
+            }
+        }
+        let _ = self.config.save();
+        self.push_system_msg(&format!("Timestamp format: {arg}."));
+    }
+
+    fn cmd_edit(&mut self, text: &str) {
+        let Some(idx) = self.chat.last_own_editable() else {
+            self.push_system_msg("No message of yours to edit.");
+            return;
+        };
+        if text.is_empty() {
+            self.start_edit(idx);
+            return;
+        }
+        if let Some(id) = self.chat.messages[idx].id.clone() {
+            self.send_net(NetCommand::EditMessage { id, text: text.to_string() });
+        }
+    }
+
+    fn cmd_delete(&mut self) {
+        let Some(idx) = self.chat.last_own_editable() else {
+            self.push_system_msg("No message of yours to delete.");
+            return;
+        };
+        if let Some(id) = self.chat.messages[idx].id.clone() {
+            self.send_net(NetCommand::DeleteMessage { id });
+        }
+    }
+
+    /// Reacts to the latest message; older ones are reached with `+` in selection mode.
+    fn cmd_react(&mut self, emoji: &str) {
+        if emoji.is_empty() {
+            self.push_system_msg("Usage: /react <emoji> reacts to the latest message (select a message and press + for others)");
+            return;
+        }
+        let target = self.chat.messages.iter().rposition(|m| m.id.is_some() && !m.deleted);
+        match target {
+            Some(idx) => self.toggle_reaction(idx, emoji),
+            None => self.push_system_msg("No message to react to."),
+        }
+    }
+
+    fn cmd_typing(&mut self, arg: &str) {
+        match arg {
+            "on" => self.config.send_typing = true,
+            "off" => {
+                self.stop_typing();
+                self.config.send_typing = false;
+            }
+            _ => {
+                let state = if self.config.send_typing { "on" } else { "off" };
+                self.push_system_msg(&format!("Typing indicator: {state}. Usage: /typing <on|off>"));
+                return;
+            }
+        }
+        let _ = self.config.save();
+        self.push_system_msg(&format!("Typing indicator {arg}."));
+    }
+
+    fn cmd_presence(&mut self, state: PresenceState, msg: &str) {
+        self.auto_idle = false;
+        let status = if msg.is_empty() { None } else { Some(msg.to_string()) };
+        self.set_presence(state, status);
+        match state {
+            PresenceState::Online => self.push_system_msg("You are back."),
+            _ if msg.is_empty() => self.push_system_msg(&format!("You are {}.", state.as_str())),
+            _ => self.push_system_msg(&format!("You are {}: {msg}", state.as_str())),
+        }
+    }
+
+    fn cmd_status(&mut self, text: &str) {
+        let state = self.chat.presence_of(&self.chat.username).state;
+        let status = if text.is_empty() { None } else { Some(text.to_string()) };
+        self.set_presence(state, status);
+        if text.is_empty() {
+            self.push_system_msg("Status cleared.");
+        } else {
+            self.push_system_msg(&format!("Status set: {text}"));
+        }
+    }
+
+    fn cmd_joins(&mut self, arg: &str) {
+        match arg {
+            "on" => self.show_join_part = true,
+            "off" => self.show_join_part = false,
+            _ => {
+                self.push_system_msg("Usage: /joins <on|off>");
+                return;
+            }
+        }
+        self.save_profile_prefs();
+        self.push_system_msg(&format!("Join/leave messages {arg}."));
+    }
+
+    fn cmd_dm(&mut self, arg: &str) {
+        let Some((to, text)) = arg.split_once(' ').filter(|(_, t)| !t.trim().is_empty()) else {
+            self.push_system_msg("Usage: /dm <user> <message>");
+            return;
+        };
+        let to = to.trim_start_matches('@').to_string();
+        self.send_net(NetCommand::SendDirect { to, text: text.trim().to_string() });
+    }
+
+    fn cmd_ignore(&mut self, user: &str, ignore: bool) {
+        let user = user.trim_start_matches('@');
+        if !user.is_empty() {
+            self.set_ignored(user, ignore);
+            return;
+        }
+        if self.config.ignored.is_empty() {
+            self.push_system_msg("Ignore list is empty. Usage: /ignore <user>");
+        } else {
+            let list = self.config.ignored.join(", ");
+            self.push_system_msg(&format!("Ignored: {list}. Usage: /unignore <user>"));
+        }
+    }
+
+    fn cmd_admin(&mut self, arg: &str) {
+        if arg.is_empty() {
+            self.open_admin(None, &[]);
+            return;
+        }
+        let words: Vec<&str> = arg.split_whitespace().collect();
+        let typed = words.get(..2)
+            .map(|w| w.join(" "))
+            .filter(|name| ADMIN_COMMANDS.iter().any(|c| c.name == name));
+        match typed {
+            Some(name) => {
+                self.open_admin(Some(&name), &words[2..]);
+                self.submit_admin();
+            }
+            None => {
+                self.send_net(NetCommand::SendAdminCmd(arg.to_string()));
+            }
+        }
+    }
+
+    fn cmd_keys(&mut self, arg: &str) {
+        let Some(wanted) = arg.strip_prefix("revoke").map(str::trim) else {
+            self.send_net(NetCommand::ListKeys);
+            return;
+        };
+        if self.chat.device_keys.is_empty() {
+            self.send_net(NetCommand::ListKeys);
+            self.push_system_msg("Fetching your device keys; run /keys revoke again once they are listed.");
+            return;
+        }
+        let own = self.pubkey_b64.clone().unwrap_or_default();
+        let wanted = wanted.to_lowercase();
+        let matches: Vec<String> = self.chat.device_keys.iter()
+            .map(|k| k.pubkey.clone())
+            .filter(|pk| !wanted.is_empty() && crypto::fingerprint(pk).is_some_and(|fp| fp.starts_with(&wanted)))
+            .collect();
+        match matches.as_slice() {
+            [pk] if *pk == own => {
+                self.push_system_msg("That is this device's key; use `ttychat keys rotate` to replace it.");
+            }
+            [pk] => {
+                self.send_net(NetCommand::RevokeKey { pubkey: pk.clone() });
+                self.push_system_msg(&format!("Revoking device key {wanted}…"));
+            }
+            [] => self.push_system_msg("Usage: /keys revoke <fingerprint> (see /keys)"),
+            _ => self.push_system_msg("Several keys match; give more of the fingerprint."),
+        }
+    }
+
+    pub fn show_device_keys(&mut self) {
+        let own = self.pubkey_b64.clone().unwrap_or_default();
+        for line in device_key_lines(&self.chat, &own) {
+            self.push_system_msg(&line);
+        }
+    }
+}
+
+pub fn device_key_lines(chat: &crate::app::ChatState, own_pubkey: &str) -> Vec<String> {
+    let mut lines = vec![format!("Device keys for {}:", chat.username)];
+    for k in &chat.device_keys {
+        let marker = if k.pubkey == own_pubkey { "*" } else { " " };
+        let fp = crypto::fingerprint(&k.pubkey).unwrap_or_else(|| "?".into());
+        let label = if k.label.is_empty() { "(unnamed)" } else { &k.label };
+        lines.push(format!("{marker} {fp}  {label}"));
+    }
+    lines.push("* = this device · /keys revoke <fingerprint>".into());
+    lines
+}
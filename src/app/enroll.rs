@@ -0,0 +1,51 @@
+use crate::app::{App, Screen};
+use crate::invite::Invite;
+use crate::net::NetCommand;
+
+impl App {
+    /// Pre-fills the connect and enroll forms from an invite link.
+    pub fn apply_invite(&mut self, invite: Invite) {
+        let address = if invite.server.contains(':') {
+            invite.server.clone()
+        } else {
+            format!("{}:{}", invite.server, self.config.default_port())
+        };
+        if self.screen == Screen::Enroll && address != self.chat.server {
+            // A different server: the next submit has to dial it.
+            self.net_cmd_tx = None;
+        }
+        self.connect_form.server = invite.server;
+        if let Some(username) = invite.username {
+            self.connect_form.username = username;
+        }
+        self.connect_form.key_file = None;
+        self.connect_form.focused_field = usize::from(self.connect_form.username.is_empty());
+        self.connect_form.invite_code = Some(invite.code.clone());
+        self.focus_on_profiles = false;
+
+        self.enroll_form.invite_code = invite.code;
+        self.enroll_form.username = self.connect_form.username.clone();
+        self.enroll_form.failure = None;
+    }
+
+    /// Sends the invite code, reusing the open connection when the server is
+    /// still waiting for one.
+    pub fn submit_enroll(&mut self) {
+        let code = self.enroll_form.invite_code.trim().to_string();
+        let username = self.enroll_form.username.trim().to_string();
+        if code.is_empty() || username.is_empty() {
+            return;
+        }
+        self.enroll_form.failure = None;
+        self.status_msg = Some("Enrolling…".into());
+        if username == self.chat.username && self.send_net(NetCommand::Enroll { code: code.clone() }) {
+            return;
+        }
+        // A new username needs its own key, so dial again.
+        if username != self.connect_form.username {
+            self.connect_form.key_file = None;
+        }
+        self.connect_form.username = username;
+        self.start_connection(Some(code));
+    }
+}
@@ -0,0 +1,245 @@
+use std::time::Instant;
+
+use crate::app::logic::{apply_filters, filter_action, parse_timestamp};
+use crate::app::verify::Trust;
+use crate::app::{ChatMessage, ChatState, Presence, PresenceState};
+use crate::config::{Config, FilterAction};
+use crate::net::ServerMsg;
+use crate::widgets::messages::render_message;
+
+/// App-wide state that handling a session's traffic reads but does not own.
+pub struct MsgContext<'a> {
+    pub config: &'a Config,
+    pub filters: &'a [(regex::Regex, FilterAction)],
+    pub show_join_part: bool,
+    pub focused: bool,
+    /// The session is not the one on screen.
+    pub background: bool,
+}
+
+/// Something a session's traffic wants the user alerted about. Raising it
+/// (counters, sound, desktop popups) is up to the app.
+pub enum Alert {
+    Message(ChatMessage),
+    Join,
+}
+
+impl ChatState {
+    pub fn apply_server_msg(&mut self, msg: ServerMsg, ctx: &MsgContext) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        let mut new_users = None;
+        if let Some(val) = msg.users {
+            if val.is_number() {
+                if let Some(count) = val.as_u64() {
+                    self.user_count = count as u32;
+                }
+            } else if let Some(arr) = val.as_array() {
+                new_users = Some(arr.iter().filter_map(|v| self.read_user_entry(v, ctx)).collect());
+            }
+        }
+
+        let lists = [msg.online, msg.names, msg.list, msg.user_list];
+        if let Some(u) = lists.into_iter().flatten().last() {
+            new_users = Some(u);
+        }
+        if let Some(users) = new_users {
+            self.update_online_users(users, ctx, &mut alerts);
+        }
+
+        if msg.msg_type == "presence" {
+            return alerts;
+        }
+
+        if msg.msg_type == "status" {
+            if let Some(from) = msg.from {
+                let state = msg.state.as_deref().and_then(PresenceState::parse).unwrap_or_default();
+                let since = msg.timestamp.as_ref().and_then(parse_timestamp);
+                let status = msg.status.filter(|s| !s.is_empty());
+                self.presence.insert(from, Presence { state, status, since });
+            }
+            return alerts;
+        }
+
+        if msg.msg_type == "reaction" {
+            let target = msg.id.as_deref().and_then(|id| self.find_by_id(id));
+            let m = target.and_then(|i| self.messages.get_mut(i));
+            if let (Some(m), Some(emoji), Some(from)) = (m, msg.emoji, msg.from) {
+                let users = m.reactions.entry(emoji.clone()).or_default();
+                users.retain(|u| u != &from);
+                if !msg.remove {
+                    users.push(from);
+                }
+                if users.is_empty() {
+                    m.reactions.remove(&emoji);
+                }
+            }
+            return alerts;
+        }
+
+        if msg.msg_type == "edit" || msg.msg_type == "delete" {
+            let target = msg.id.as_deref().and_then(|id| self.find_by_id(id));
+            if let Some(m) = target.and_then(|i| self.messages.get_mut(i)) {
+                if msg.msg_type == "delete" {
+                    m.deleted = true;
+                    m.text.clear();
+                } else if let Some(text) = msg.text {
+                    m.text = text;
+                    m.edited = true;
+                    if !m.is_system && m.from != self.username {
+                        apply_filters(ctx.config, ctx.filters, m);
+                    }
+                }
+            }
+            return alerts;
+        }
+
+        let from = msg.from.unwrap_or_else(|| "system".into());
+        let text = msg.text.unwrap_or_default();
+
+        let timestamp = msg.timestamp.as_ref()
+            .and_then(parse_timestamp)
+            .unwrap_or_else(chrono::Local::now);
+
+        self.typing.remove(&from);
+        let is_direct = msg.msg_type == "dm" || msg.to.is_some();
+        let cm = ChatMessage {
+            id: msg.id,
+            reply_to: msg.reply_to,
+            from,
+            text,
+            timestamp,
+            is_direct,
+            ..Default::default()
+        };
+        alerts.extend(self.push_message(cm, ctx));
+        alerts
+    }
+
+    /// Appends a message, keeping a scrolled view in place. Returns an alert
+    /// if the user should hear about it.
+    pub fn push_message(&mut self, mut msg: ChatMessage, ctx: &MsgContext) -> Option<Alert> {
+        let is_own = msg.from == self.username;
+        if !msg.is_system && !is_own {
+            match filter_action(ctx.config, ctx.filters, &msg) {
+                Some(FilterAction::Hide) if !ctx.config.hidden_placeholder => return None,
+                Some(FilterAction::Hide) => msg.hidden = true,
+                Some(FilterAction::Dim) => msg.dimmed = true,
+                None => {}
+            }
+        }
+        if self.scroll_offset > 0 {
+            let (width, _) = self.msg_view.get();
+            let parent = msg.reply_to.as_deref()
+                .and_then(|id| self.find_by_id(id))
+                .map(|i| &self.messages[i]);
+            let mut rows = render_message(&msg, parent, &self.username, &ctx.config.timestamp, width).len();
+            // layout_messages puts a day divider before the first message of each day.
+            let day = msg.timestamp.date_naive();
+            if self.messages.last().map(|m| m.timestamp.date_naive()) != Some(day) {
+                rows += 1;
+            }
+            self.scroll_offset = self.scroll_offset.saturating_add(rows);
+            if !is_own && !msg.is_system && !msg.hidden {
+                self.new_below += 1;
+            }
+        }
+        if ctx.background && !is_own && !msg.is_system && !msg.hidden {
+            self.unread += 1;
+        }
+        self.messages.push(msg.clone());
+        self.trim_history(ctx.config.history_size());
+
+        if msg.is_system || is_own || ctx.focused || msg.hidden || msg.dimmed {
+            return None;
+        }
+        if self.marker_pending {
+            self.marker_pending = false;
+            self.unread_marker = Some(self.messages.len() - 1);
+            if self.scroll_offset > 0 {
+                self.scroll_offset += 1;
+            }
+        }
+        Some(Alert::Message(msg))
+    }
+
+    pub fn push_system(&mut self, text: &str, ctx: &MsgContext) {
+        let cm = ChatMessage {
+            from: "─ sys ─".into(),
+            text: text.into(),
+            timestamp: chrono::Local::now(),
+            is_system: true,
+            ..Default::default()
+        };
+        self.push_message(cm, ctx);
+    }
+
+    pub fn set_typing(&mut self, user: String, active: bool) {
+        if user == self.username {
+            return;
+        }
+        if active {
+            self.typing.insert(user, Instant::now());
+        } else {
+            self.typing.remove(&user);
+        }
+    }
+
+    fn update_online_users(&mut self, users: Vec<String>, ctx: &MsgContext, alerts: &mut Vec<Alert>) {
+        let previous = std::mem::replace(&mut self.online_users, users);
+        if !self.users_known {
+            self.users_known = true;
+            return;
+        }
+
+        let current = &self.online_users;
+        let joined: Vec<String> = current.iter().filter(|u| !previous.contains(u)).cloned().collect();
+        let left: Vec<String> = previous.iter().filter(|u| !current.contains(u)).cloned().collect();
+        for u in &left {
+            self.presence.remove(u);
+            self.typing.remove(u);
+        }
+
+        if !joined.is_empty() {
+            alerts.push(Alert::Join);
+        }
+        if ctx.show_join_part {
+            self.announce_presence_change(&joined, "→", "joined", ctx);
+            self.announce_presence_change(&left, "←", "left", ctx);
+        }
+    }
+
+    fn announce_presence_change(&mut self, users: &[String], arrow: &str, verb: &str, ctx: &MsgContext) {
+        const COLLAPSE_AFTER: usize = 3;
+        if users.len() > COLLAPSE_AFTER {
+            self.push_system(&format!("{arrow} {} users {verb}", users.len()), ctx);
+        } else {
+            for u in users {
+                self.push_system(&format!("{arrow} {u} {verb}"), ctx);
+            }
+        }
+    }
+
+    fn read_user_entry(&mut self, v: &serde_json::Value, ctx: &MsgContext) -> Option<String> {
+        if let Some(name) = v.as_str() {
+            return Some(name.to_string());
+        }
+        let name = v.get("name").or_else(|| v.get("username"))?.as_str()?.to_string();
+        if let Some(key) = v.get("pubkey").and_then(|k| k.as_str()) {
+            let previous = self.user_keys.insert(name.clone(), key.to_string());
+            if previous.as_deref() != Some(key) && self.trust_of(ctx.config, &name) == Trust::Changed {
+                self.push_system(&format!("⚠ {name}'s key differs from the one you verified. Compare safety numbers again."), ctx);
+            }
+        }
+        let state = v.get("state").and_then(|s| s.as_str()).and_then(PresenceState::parse);
+        let status = v.get("status").and_then(|s| s.as_str()).filter(|s| !s.is_empty());
+        if state.is_some() || status.is_some() {
+            let since = v.get("since").and_then(parse_timestamp);
+            self.presence.insert(name.clone(), Presence {
+                state: state.unwrap_or_default(),
+                status: status.map(str::to_string),
+                since,
+            });
+        }
+        Some(name)
+    }
+}
@@ -1,18 +1,27 @@
 use anyhow::Result;
-use base64::Engine;
+use chrono::{DateTime, Local, NaiveDateTime};
 use crossterm::{event::{KeyCode, KeyModifiers}, execute};
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io::{self, Stdout};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
-use crate::app::{App, Screen, ChatMessage};
-use crate::config::Config;
+use crate::app::incoming::{Alert, MsgContext};
+use crate::app::{App, ChatMessage, Presence, PresenceState, Screen};
+use crate::config::{Config, FilterAction, NotificationPrefs};
 use crate::crypto;
-use crate::events::{spawn_event_task, AppEvent};
-use crate::net::{self, NetCommand, NetEvent, ServerMsg};
+use crate::events::{spawn_config_watcher, spawn_event_task, AppEvent};
+use crate::invite::Invite;
+use crate::keymap::{Action, KeyContext, KeyResult, Scope};
+use crate::net::{self, AuthFailure, NetCommand, NetEvent, ServerMsg};
+use crate::notify::{self, DesktopNotification, SoundEvent};
 use crate::ui;
 
+const TYPING_RESEND: Duration = Duration::from_secs(3);
+const TYPING_IDLE: Duration = Duration::from_secs(5);
+const TYPING_EXPIRY: Duration = Duration::from_secs(6);
+
 impl App {
     pub async fn run(
         mut self,
@@ -35,34 +44,19 @@ impl App {
     pub fn start_connection(&mut self, enroll_code: Option<String>) {
         let username = self.connect_form.username.trim().to_string();
         if username.is_empty() { return; }
-
-        let key_path = Config::key_path(&username);
-        if !self.connect_form.manual_key.trim().is_empty() {
-            let key_str = self.connect_form.manual_key.trim();
-            if let Ok(seed) = base64::engine::general_purpose::STANDARD.decode(key_str) {
-                let seed: Vec<u8> = seed;
-                if seed.len() == 32 {
-                    if let Some(parent) = key_path.parent() {
-                        let _ = std::fs::create_dir_all(parent);
-                    }
-                    let _ = std::fs::write(&key_path, seed);
-                    self.status_msg = Some("Imported manual key".into());
-                } else {
-                    self.screen = Screen::Error("Invalid manual key length (expected 32 bytes)".into());
-                    return;
-                }
-            } else {
-                self.screen = Screen::Error("Invalid manual key format (expected Base64)".into());
-                return;
-            }
+        let key_path = self.connect_form.key_file.clone()
+            .unwrap_or_else(|| Config::key_path(&username));
+        if !self.import_manual_key(&key_path) {
+            return;
         }
+        self.park_active_session();
 
         match crypto::load_or_generate(&key_path) {
             Ok((key, is_new)) => {
                 let pubkey = crypto::pubkey_b64(&key);
                 self.pubkey_b64 = Some(pubkey);
                 self.is_new_key = is_new;
-                self.signing_key = Some(Arc::new(key));
+                self.signing_key = Some(Arc::new(crypto::LockedKey::new(key)));
                 
                 if is_new && enroll_code.is_none() {
                     self.screen = Screen::KeyInfo;
@@ -82,14 +76,23 @@ impl App {
         let server = if raw_server.contains(':') {
             raw_server
         } else {
-            format!("{}:7000", raw_server)
+            format!("{}:{}", raw_server, self.config.default_port())
         };
         let insecure = self.connect_form.insecure;
 
-        let (event_tx, event_rx) = mpsc::unbounded_channel::<NetEvent>();
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel::<NetEvent>();
         let (cmd_tx, cmd_rx) = mpsc::unbounded_channel::<NetCommand>();
         self.net_cmd_tx = Some(cmd_tx);
-        self.net_event_rx = Some(event_rx);
+
+        let tagged_tx = self.net_event_tx.clone();
+        let session_id = self.session_id;
+        tokio::spawn(async move {
+            while let Some(evt) = event_rx.recv().await {
+                if tagged_tx.send((session_id, evt)).is_err() {
+                    break;
+                }
+            }
+        });
 
         let params = net::NetParams {
             server: server.clone(),
@@ -116,6 +119,8 @@ impl App {
     ) -> Result<()> {
         let (app_event_tx, mut app_event_rx) = mpsc::unbounded_channel::<AppEvent>();
         self.app_event_tx = Some(app_event_tx.clone());
+        self.notifier = Some(notify::spawn_desktop_notifier());
+        spawn_config_watcher(self.config.path.clone(), app_event_tx.clone());
         spawn_event_task(app_event_tx);
 
         loop {
@@ -126,8 +131,8 @@ impl App {
                 while let Ok(evt) = rx.try_recv() {
                     events.push(evt);
                 }
-                for evt in events {
-                    if self.handle_net_event(evt) {
+                for (id, evt) in events {
+                    if self.handle_session_event(id, evt) {
                         quit = true;
                         break;
                     }
@@ -147,12 +152,15 @@ impl App {
             match event {
                 AppEvent::Tick => {
                     self.tick_count = self.tick_count.wrapping_add(1);
+                    self.expire_typing();
+                    self.check_idle();
                     if self.screen == Screen::Splash && self.tick_count >= 30 && !self.splash_done {
-                        self.splash_done = true;
-                        self.screen = Screen::Connect;
+                        self.finish_splash();
                     }
                 }
                 AppEvent::Resize => {}
+                AppEvent::Paste(text) => self.handle_paste(&text),
+                AppEvent::ConfigReloaded => self.reload_config(),
                 AppEvent::FocusGained => {
                     self.terminal_focused = true;
                     if self.unread_count > 0 {
@@ -162,8 +170,15 @@ impl App {
                 }
                 AppEvent::FocusLost => {
                     self.terminal_focused = false;
+                    self.chat.marker_pending = true;
                 }
                 AppEvent::Key(key) => {
+                    self.last_key_at = Instant::now();
+                    if self.auto_idle {
+                        self.auto_idle = false;
+                        let status = self.chat.presence_of(&self.chat.username).status;
+                        self.set_presence(PresenceState::Online, status);
+                    }
                     if self.handle_key(key) {
                         return Ok(());
                     }
@@ -173,156 +188,374 @@ impl App {
         Ok(())
     }
 
-    fn handle_net_event(&mut self, evt: NetEvent) -> bool {
+    pub fn handle_net_event(&mut self, evt: NetEvent) -> bool {
         match evt {
             NetEvent::Connected => {
                 self.status_msg = Some("Authenticating…".into());
             }
-            NetEvent::AuthOk { username } => {
+            NetEvent::AuthOk { username, is_admin } => {
                 self.screen = Screen::Chat;
+                self.is_admin = is_admin;
+                self.chat.authenticated = true;
                 self.status_msg = None;
                 self.chat.username = username.clone();
+                self.chat.users_known = false;
                 self.config.last_server = Some(self.chat.server.clone());
                 self.config.last_username = Some(self.chat.username.clone());
                 
-                let mut profiles = self.config.profiles.clone();
-                profiles.retain(|p| p.server != self.chat.server || p.username != username);
-                profiles.insert(0, crate::config::ServerProfile {
-                    server: self.chat.server.clone(),
-                    username: username.clone(),
-                });
-                self.config.profiles = profiles.into_iter().take(10).collect();
+                let profile = self.config.remember_profile(&self.chat.server, &username);
+                self.notif_prefs = profile.notifications;
+                self.show_join_part = profile.show_join_part;
                 self.profiles = self.config.profiles.clone();
 
                 let _ = self.config.save();
+                self.connect_form.invite_code = None;
+                self.enroll_form = Default::default();
             }
             NetEvent::AuthFail { reason } => {
                 self.screen = Screen::Enroll;
-                self.status_msg = Some(format!("Auth failed: {reason}. Enter invite code to enroll."));
+                self.status_msg = None;
+                self.enroll_form.focused = usize::from(reason == AuthFailure::UsernameTaken);
+                if self.enroll_form.username.is_empty() || reason == AuthFailure::UsernameTaken {
+                    self.enroll_form.username = self.chat.username.clone();
+                }
+                self.enroll_form.failure = Some(reason);
             }
             NetEvent::Message(msg) => {
                 self.handle_server_msg(*msg);
             }
-            NetEvent::AdminResponse { action, data } => {
-                self.chat.admin_response = Some(format!("[ADMIN >> {action}] {data}"));
-                let cm = ChatMessage {
-                    from: "ADMIN".into(),
-                    text: format!("[{action}] {data}"),
-                    timestamp: chrono::Local::now().format("%H:%M").to_string(),
-                    is_system: true,
-                    is_admin: true,
-                };
-                self.push_message(cm);
+            NetEvent::DeviceKeys(keys) => {
+                self.chat.device_keys = keys;
+                self.show_device_keys();
+            }
+            NetEvent::AdminResponse { action, data, req_id } => {
+                self.on_admin_response(action, data, req_id);
+            }
+            NetEvent::Typing { user, active } => self.chat.set_typing(user, active),
+            NetEvent::Notice(text) => self.push_system_msg(&text),
+            NetEvent::Error(e) if self.chat.authenticated => {
+                self.push_system_msg(&format!("Connection error: {e}"));
+                self.net_cmd_tx = None;
             }
             NetEvent::Error(e) => {
                 self.screen = Screen::Error(e);
             }
             NetEvent::Disconnected => {
-                if self.screen == Screen::Chat {
+                if self.chat.authenticated {
                     self.push_system_msg("Disconnected from server");
-                    self.net_cmd_tx = None;
                 }
+                self.net_cmd_tx = None;
             }
         }
         false
     }
 
     fn handle_server_msg(&mut self, msg: ServerMsg) {
-        if let Some(val) = msg.users {
-            if val.is_number() {
-                if let Some(count) = val.as_u64() {
-                    self.chat.user_count = count as u32;
+        let ctx = MsgContext {
+            config: &self.config,
+            filters: &self.filters,
+            show_join_part: self.show_join_part,
+            focused: self.terminal_focused,
+            background: false,
+        };
+        let alerts = self.chat.apply_server_msg(msg, &ctx);
+        let (prefs, username) = (self.notif_prefs.clone(), self.chat.username.clone());
+        self.raise_alerts(alerts, &prefs, &username);
+    }
+
+    pub fn push_message(&mut self, msg: ChatMessage) {
+        let ctx = MsgContext {
+            config: &self.config,
+            filters: &self.filters,
+            show_join_part: self.show_join_part,
+            focused: self.terminal_focused,
+            background: false,
+        };
+        let alert = self.chat.push_message(msg, &ctx);
+        let (prefs, username) = (self.notif_prefs.clone(), self.chat.username.clone());
+        self.raise_alerts(alert, &prefs, &username);
+    }
+
+    /// Bumps the unread counters and plays sounds or shows popups for a
+    /// session's alerts, following that session's notification prefs.
+    pub fn raise_alerts(&mut self, alerts: impl IntoIterator<Item = Alert>, prefs: &NotificationPrefs, username: &str) {
+        let now = chrono::Local::now().time();
+        for alert in alerts {
+            let msg = match alert {
+                Alert::Join => {
+                    if !self.terminal_focused && prefs.sound && !prefs.muted && !prefs.is_quiet(now) {
+                        self.audio.play(SoundEvent::Join);
+                    }
+                    continue;
                 }
-            } else if val.is_array() {
-                if let Some(arr) = val.as_array() {
-                    self.chat.online_users = arr.iter()
-                        .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                        .collect();
+                Alert::Message(msg) => msg,
+            };
+            // Muted senders and quiet hours leave the title alone too; the
+            // unread marker in the chat still tracks every message.
+            if !prefs.allows(&msg.from, now) {
+                continue;
+            }
+            self.unread_count += 1;
+            if prefs.title_counter {
+                let title = format!("({} unread) ttychat", self.unread_count);
+                let _ = execute!(io::stdout(), crossterm::terminal::SetTitle(title.as_str()));
+            }
+            if prefs.sound {
+                let event = if msg.is_direct {
+                    SoundEvent::DirectMessage
+                } else if mentions(&msg.text, username) {
+                    SoundEvent::Mention
+                } else {
+                    SoundEvent::Message
+                };
+                self.audio.play(event);
+            }
+            if prefs.desktop {
+                if let Some(tx) = &self.notifier {
+                    let _ = tx.send(DesktopNotification { from: msg.from, text: msg.text });
                 }
             }
         }
+    }
+
+    pub fn toggle_ignore(&mut self, user: &str) {
+        let ignored = self.config.ignored.iter().any(|u| u == user);
+        self.set_ignored(user, !ignored);
+    }
+
+    pub fn set_ignored(&mut self, user: &str, ignore: bool) {
+        self.config.ignored.retain(|u| u != user);
+        if ignore {
+            self.config.ignored.push(user.to_string());
+        }
+        self.refilter_messages();
+        let _ = self.config.save();
+        let verb = if ignore { "ignored" } else { "no longer ignored" };
+        self.push_system_msg(&format!("{user} {verb}."));
+    }
 
-        if let Some(u) = msg.online { self.chat.online_users = u; }
-        if let Some(u) = msg.names { self.chat.online_users = u; }
-        if let Some(u) = msg.list { self.chat.online_users = u; }
-        if let Some(u) = msg.user_list { self.chat.online_users = u; }
+    pub fn set_presence(&mut self, state: PresenceState, status: Option<String>) {
+        let since = (state != PresenceState::Online).then(Local::now);
+        self.chat.presence.insert(self.chat.username.clone(), Presence {
+            state,
+            status: status.clone(),
+            since,
+        });
+        self.send_net(NetCommand::SetPresence { state: state.as_str().into(), status });
+    }
 
-        if msg.msg_type == "presence" {
+    fn check_idle(&mut self) {
+        if self.screen != Screen::Chat || self.config.idle_after_mins == 0 {
             return;
         }
+        let me = self.chat.presence_of(&self.chat.username);
+        let limit = Duration::from_secs(self.config.idle_after_mins as u64 * 60);
+        if me.state == PresenceState::Online && self.last_key_at.elapsed() >= limit {
+            self.auto_idle = true;
+            self.set_presence(PresenceState::Idle, me.status);
+        }
+    }
 
-        let from = msg.from.unwrap_or_else(|| "system".into());
-        let text = msg.text.unwrap_or_default();
-        
-        let timestamp = if let Some(ts_val) = &msg.timestamp {
-            if let Some(ts_str) = ts_val.as_str() {
-                if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(ts_str) {
-                    dt.with_timezone(&chrono::Local).format("%H:%M").to_string()
-                } else {
-                    ts_str.chars().take(5).collect()
-                }
-            } else if let Some(ts_num) = ts_val.as_f64().map(|f| f as i64) {
-                let dt = if ts_num > 10_000_000_000 {
-                    chrono::DateTime::from_timestamp(ts_num / 1000, (ts_num % 1000) as u32 * 1_000_000)
-                } else {
-                    chrono::DateTime::from_timestamp(ts_num, 0)
-                };
-                dt.and_then(|d| Some(d.with_timezone(&chrono::Local).format("%H:%M").to_string()))
-                  .unwrap_or_else(|| chrono::Local::now().format("%H:%M").to_string())
-            } else {
-                chrono::Local::now().format("%H:%M").to_string()
-            }
-        } else {
-            chrono::Local::now().format("%H:%M").to_string()
+    pub fn send_net(&self, cmd: NetCommand) -> bool {
+        self.net_cmd_tx.as_ref().is_some_and(|tx| tx.send(cmd).is_ok())
+    }
+
+    pub fn start_edit(&mut self, idx: usize) {
+        let Some(msg) = self.chat.messages.get(idx) else { return };
+        let Some(id) = msg.id.clone() else { return };
+        self.chat.input = msg.text.clone();
+        self.chat.input_cursor = self.chat.input.chars().count();
+        self.chat.editing = Some(id);
+        self.chat.replying_to = None;
+    }
+
+    pub fn toggle_reaction(&mut self, idx: usize, emoji: &str) {
+        let Some(msg) = self.chat.messages.get(idx) else { return };
+        let Some(id) = msg.id.clone() else {
+            self.chat.flash = Some(("This message can't take reactions".into(), self.tick_count + 35));
+            return;
         };
+        let remove = msg.reactions.get(emoji).is_some_and(|u| u.contains(&self.chat.username));
+        self.send_net(NetCommand::React { id, emoji: emoji.to_string(), remove });
+    }
+
+    pub fn on_input_changed(&mut self) {
+        let input = &self.chat.input;
+        if input.is_empty() || input.starts_with('/') || self.chat.editing.is_some() {
+            self.stop_typing();
+            return;
+        }
+        let now = Instant::now();
+        self.chat.last_input_edit = Some(now);
+        let due = self.chat.typing_sent.is_none_or(|t| now.duration_since(t) >= TYPING_RESEND);
+        if self.config.send_typing && due && self.send_net(NetCommand::Typing(true)) {
+            self.chat.typing_sent = Some(now);
+        }
+    }
 
-        let cm = ChatMessage { from, text: text.clone(), timestamp, is_system: false, is_admin: false };
-        self.push_message(cm);
+    pub fn stop_typing(&mut self) {
+        if self.chat.typing_sent.take().is_some() {
+            self.send_net(NetCommand::Typing(false));
+        }
     }
 
-    fn push_message(&mut self, msg: ChatMessage) {
-        self.chat.messages.push(msg.clone());
+    fn expire_typing(&mut self) {
+        self.chat.typing.retain(|_, at| at.elapsed() < TYPING_EXPIRY);
+        let idle = self.chat.last_input_edit.is_some_and(|t| t.elapsed() >= TYPING_IDLE);
+        if idle {
+            self.stop_typing();
+        }
+    }
 
-        let is_own = msg.from == self.chat.username;
-        if !msg.is_system && !is_own && !self.terminal_focused && !self.notifications_muted {
-            self.unread_count += 1;
-            let title = format!("({} unread) ttychat", self.unread_count);
-            let _ = execute!(io::stdout(), crossterm::terminal::SetTitle(title.as_str()));
-            let notif_from = msg.from.clone();
-            let notif_text = msg.text.clone();
-            tokio::task::spawn_blocking(move || {
-                crate::notify::play_notification_sound();
-                crate::notify::send_desktop_notification(&notif_from, &notif_text);
-            });
+    pub fn save_profile_prefs(&mut self) {
+        let (server, username) = (&self.chat.server, &self.chat.username);
+        let port = self.config.default_port();
+        if let Some(p) = self.config.profiles.iter_mut().find(|p| p.matches(server, username, port)) {
+            p.notifications = self.notif_prefs.clone();
+            p.show_join_part = self.show_join_part;
+            self.profiles = self.config.profiles.clone();
+            let _ = self.config.save();
         }
     }
 
-    fn push_system_msg(&mut self, text: &str) {
-        let cm = ChatMessage {
+    pub fn push_system_msg(&mut self, text: &str) {
+        self.push_message(ChatMessage {
             from: "─ sys ─".into(),
             text: text.into(),
-            timestamp: chrono::Local::now().format("%H:%M").to_string(),
+            timestamp: chrono::Local::now(),
             is_system: true,
-            is_admin: false,
-        };
-        self.push_message(cm);
+            ..Default::default()
+        });
+    }
+
+    /// Invite links fill in the connect form; any other paste goes into the
+    /// focused text field as-is, so it cannot trigger bindings or send anything.
+    fn handle_paste(&mut self, text: &str) {
+        let forms = matches!(self.screen, Screen::Connect | Screen::Enroll) && self.profile_editor.is_none();
+        if forms {
+            if let Some(invite) = Invite::parse(text) {
+                self.apply_invite(invite);
+                return;
+            }
+        }
+        let line: String = text.chars()
+            .filter(|&c| c != '\r')
+            .map(|c| if c == '\n' { ' ' } else { c })
+            .collect();
+        let word = line.trim();
+        match self.screen {
+            Screen::Connect => {
+                let form = &mut self.connect_form;
+                if let Some(editor) = self.profile_editor.as_mut() {
+                    if let Some(field) = editor.fields.get_mut(editor.focused) {
+                        field.push_str(word);
+                    }
+                } else if let Some(pass) = form.backup_passphrase.as_mut() {
+                    pass.push_str(text.trim_end_matches(['\r', '\n']));
+                } else if !self.focus_on_profiles {
+                    match form.focused_field {
+                        0 => form.server.push_str(word),
+                        1 => form.username.push_str(word),
+                        2 => {
+                            form.key_file = None;
+                            form.manual_key.push_str(text.trim());
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Screen::Enroll => {
+                let form = &mut self.enroll_form;
+                let field = if form.focused == 0 { &mut form.invite_code } else { &mut form.username };
+                field.push_str(word);
+            }
+            Screen::Filters => {
+                if let Some(input) = self.filters_form.input.as_mut() {
+                    input.push_str(&line);
+                }
+            }
+            Screen::Admin => {
+                if let Some(field) = self.admin.field.and_then(|f| self.admin.fields.get_mut(f)) {
+                    field.push_str(&line);
+                }
+            }
+            Screen::Chat if self.chat.input_focused() && !line.is_empty() => {
+                self.chat.insert_input(&line);
+                self.on_input_changed();
+            }
+            _ => {}
+        }
     }
 
     fn handle_key(&mut self, key: crossterm::event::KeyEvent) -> bool {
-        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
-            return true;
+        if self.confirm_quit {
+            self.confirm_quit = false;
+            return matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter);
+        }
+
+        if let KeyCode::Char(c @ '1'..='9') = key.code {
+            if key.modifiers.contains(KeyModifiers::ALT) {
+                let tabs = self.session_tabs();
+                if let Some(tab) = tabs.get(c as usize - '1' as usize).filter(|t| !t.active) {
+                    self.switch_session(tab.id);
+                }
+                return false;
+            }
         }
 
-        if self.screen == Screen::Chat && key.code == KeyCode::Tab {
+        let actions = match self.keymap.feed(&key, &self.key_context()) {
+            KeyResult::Actions(actions) => actions,
+            KeyResult::Pending => return false,
+            KeyResult::Unbound => Vec::new(),
+            KeyResult::Broken(held) => {
+                for k in held {
+                    if self.dispatch_key(k, Vec::new()) {
+                        return true;
+                    }
+                }
+                return self.handle_key(key);
+            }
+        };
+        self.dispatch_key(key, actions)
+    }
+
+    fn key_context(&self) -> KeyContext {
+        match self.screen {
+            Screen::Chat => KeyContext {
+                scope: Scope::Chat,
+                typing: self.chat.input_focused(),
+                can_edit_last: self.chat.can_edit_last(),
+            },
+            Screen::Connect => KeyContext {
+                scope: Scope::Form,
+                typing: !self.focus_on_profiles || self.profile_editor.is_some()
+                    || self.connect_form.backup_passphrase.is_some(),
+                ..Default::default()
+            },
+            Screen::Enroll => KeyContext { scope: Scope::Form, typing: true, ..Default::default() },
+            Screen::Filters => KeyContext { typing: self.filters_form.input.is_some(), ..Default::default() },
+            Screen::Admin => KeyContext { typing: self.admin.field.is_some(), ..Default::default() },
+            _ => KeyContext::default(),
+        }
+    }
+
+    fn dispatch_key(&mut self, key: crossterm::event::KeyEvent, actions: Vec<Action>) -> bool {
+        if actions.contains(&Action::Quit) {
+            if !self.config.keys.confirm_quit {
+                return true;
+            }
+            self.confirm_quit = true;
+            return false;
+        }
+
+        if self.screen == Screen::Chat && actions.contains(&Action::ToggleUsers) {
             self.chat.focus_users = !self.chat.focus_users;
+            self.chat.user_popup = None;
             return false;
         }
 
         match &self.screen.clone() {
-            Screen::Splash => {
-                self.splash_done = true;
-                self.screen = Screen::Connect;
-            }
+            Screen::Splash => self.finish_splash(),
             Screen::KeyInfo => {
                 if key.code == KeyCode::Enter || key.code == KeyCode::Char('c') {
                     self.screen = Screen::Connect;
@@ -330,31 +563,21 @@ impl App {
             }
             Screen::Connect => {
                 match key.code {
-                    KeyCode::Up if self.focus_on_profiles => {
-                        if !self.profiles.is_empty() {
-                            let idx = self.selected_profile.unwrap_or(0);
-                            self.selected_profile = Some(idx.saturating_sub(1));
-                        }
-                    }
-                    KeyCode::Down if self.focus_on_profiles => {
-                        if !self.profiles.is_empty() {
-                            let idx = self.selected_profile.unwrap_or(0);
-                            self.selected_profile = Some((idx + 1).min(self.profiles.len() - 1));
-                        }
+                    _ if self.profile_editor.is_some() => ui::profiles::handle_editor_key(self, key),
+                    _ if self.connect_form.backup_passphrase.is_some() => ui::connect::handle_key(self, key, &actions),
+                    KeyCode::Esc if self.chat.authenticated => {
+                        self.clear_key_entry();
+                        self.screen = Screen::Chat;
                     }
-                    KeyCode::Left | KeyCode::Right if !self.profiles.is_empty() => {
+                    KeyCode::Left | KeyCode::Right => {
                         self.focus_on_profiles = !self.focus_on_profiles;
-                    }
-                    KeyCode::Enter if self.focus_on_profiles => {
-                        if let Some(idx) = self.selected_profile {
-                            if let Some(p) = self.profiles.get(idx).cloned() {
-                                self.connect_form.server = p.server;
-                                self.connect_form.username = p.username;
-                                self.start_connection(None);
-                            }
+                        self.profile_delete_armed = None;
+                        if self.selected_profile.is_none() && !self.profiles.is_empty() {
+                            self.selected_profile = Some(0);
                         }
                     }
-                    _ => ui::connect::handle_key(self, key),
+                    _ if self.focus_on_profiles => ui::profiles::handle_sidebar_key(self, key),
+                    _ => ui::connect::handle_key(self, key, &actions),
                 }
             }
             Screen::Auth => {
@@ -363,9 +586,11 @@ impl App {
                     self.net_cmd_tx = None;
                 }
             }
-            Screen::Enroll => ui::screens::handle_enroll_key(self, key),
+            Screen::Enroll => ui::screens::handle_enroll_key(self, key, &actions),
+            Screen::Filters => ui::screens::handle_filters_key(self, key),
+            Screen::Admin => ui::screens::handle_admin_key(self, key),
             Screen::Chat => {
-                if ui::chat::handle_key(self, key) { return true; }
+                if ui::chat::handle_key(self, key, &actions) { return true; }
             }
             Screen::Error(_) => {
                 if key.code == KeyCode::Enter || key.code == KeyCode::Esc {
@@ -376,3 +601,52 @@ impl App {
         false
     }
 }
+
+pub fn filter_action(config: &Config, filters: &[(regex::Regex, FilterAction)], msg: &ChatMessage) -> Option<FilterAction> {
+    if config.ignored.contains(&msg.from) {
+        return Some(FilterAction::Hide);
+    }
+    filters.iter()
+        .find(|(re, _)| re.is_match(&msg.text))
+        .map(|(_, action)| *action)
+}
+
+/// Recomputes hide/dim for a message already in the history.
+pub fn apply_filters(config: &Config, filters: &[(regex::Regex, FilterAction)], msg: &mut ChatMessage) {
+    let action = filter_action(config, filters, msg);
+    msg.hidden = action == Some(FilterAction::Hide);
+    msg.dimmed = action == Some(FilterAction::Dim);
+}
+
+fn mentions(text: &str, username: &str) -> bool {
+    if username.is_empty() {
+        return false;
+    }
+    text.split(|c: char| !c.is_alphanumeric() && c != '_' && c != '-')
+        .any(|word| word.eq_ignore_ascii_case(username))
+}
+
+pub fn parse_timestamp(val: &serde_json::Value) -> Option<DateTime<Local>> {
+    if let Some(s) = val.as_str() {
+        if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+            return Some(dt.with_timezone(&Local));
+        }
+        if let Ok(n) = s.parse::<f64>() {
+            return parse_timestamp(&serde_json::json!(n));
+        }
+        ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M"]
+            .iter()
+            .find_map(|f| NaiveDateTime::parse_from_str(s, f).ok())
+            // No offset given: take it as wall-clock time, which is what the
+            // server showed before timestamps were parsed.
+            .and_then(|naive| naive.and_local_timezone(Local).earliest())
+    } else {
+        let n = val.as_f64()? as i64;
+        let dt = if n > 10_000_000_000 {
+            DateTime::from_timestamp_millis(n)
+        } else {
+            DateTime::from_timestamp(n, 0)
+        };
+        dt.map(|d| d.with_timezone(&Local))
+    }
+}
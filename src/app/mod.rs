@@ -1,4 +1,13 @@
 pub mod state;
 pub mod logic;
+pub mod commands;
+pub mod admin;
+pub mod session;
+pub mod profiles;
+pub mod reload;
+pub mod enroll;
+pub mod verify;
+pub mod backup;
+pub mod incoming;
 
 pub use state::*;
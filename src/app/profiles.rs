@@ -0,0 +1,145 @@
+use std::path::PathBuf;
+
+use crate::app::{App, ProfileEditor, Screen};
+use crate::config::ServerProfile;
+
+impl ProfileEditor {
+    pub fn new_from(profile: &ServerProfile, index: Option<usize>) -> Self {
+        Self {
+            index,
+            fields: [
+                profile.name.clone(),
+                profile.server.clone(),
+                profile.port.map(|p| p.to_string()).unwrap_or_default(),
+                profile.username.clone(),
+                profile.key_file.as_ref().map(|p| p.display().to_string()).unwrap_or_default(),
+                profile.notes.clone(),
+            ],
+            insecure: profile.insecure,
+            auto_connect: profile.auto_connect,
+            ..Default::default()
+        }
+    }
+
+    /// Writes the edited fields into `profile`, validating them first.
+    pub fn apply(&self, profile: &mut ServerProfile) -> Result<(), String> {
+        let [name, server, port, username, key_file, notes] = &self.fields;
+        if server.trim().is_empty() || username.trim().is_empty() {
+            return Err("Server and username are required".into());
+        }
+        let port = match port.trim() {
+            "" => None,
+            p => Some(p.parse::<u16>().map_err(|_| format!("Invalid port: {p}"))?),
+        };
+        profile.name = name.trim().to_string();
+        profile.server = server.trim().to_string();
+        profile.port = port;
+        profile.username = username.trim().to_string();
+        profile.key_file = Some(key_file.trim()).filter(|k| !k.is_empty()).map(PathBuf::from);
+        profile.notes = notes.trim().to_string();
+        profile.insecure = self.insecure;
+        profile.auto_connect = self.auto_connect;
+        Ok(())
+    }
+}
+
+impl App {
+    pub fn finish_splash(&mut self) {
+        self.splash_done = true;
+        self.screen = Screen::Connect;
+        if let Some(idx) = self.profiles.iter().position(|p| p.auto_connect) {
+            self.selected_profile = Some(idx);
+            self.connect_profile(idx);
+        }
+    }
+
+    pub fn connect_profile(&mut self, idx: usize) {
+        let Some(p) = self.profiles.get(idx).cloned() else { return };
+        self.connect_form.server = p.address(self.config.default_port());
+        self.connect_form.username = p.username;
+        self.connect_form.insecure = p.insecure;
+        self.connect_form.key_file = p.key_file;
+        self.start_connection(None);
+    }
+
+    pub fn save_profile_editor(&mut self) {
+        let Some(editor) = self.profile_editor.as_mut() else { return };
+        let mut profile = match editor.index.and_then(|i| self.config.profiles.get(i)) {
+            Some(p) => p.clone(),
+            None => ServerProfile::new("", ""),
+        };
+        if let Err(e) = editor.apply(&mut profile) {
+            editor.error = Some(e);
+            return;
+        }
+        let editor = self.profile_editor.take().unwrap_or_default();
+        if profile.auto_connect {
+            for p in &mut self.config.profiles {
+                p.auto_connect = false;
+            }
+        }
+        let idx = match editor.index {
+            Some(i) if i < self.config.profiles.len() => {
+                self.config.profiles[i] = profile;
+                i
+            }
+            _ => {
+                let at = self.config.profiles.iter().take_while(|p| p.pinned).count();
+                self.config.profiles.insert(at, profile);
+                at
+            }
+        };
+        self.selected_profile = Some(idx);
+        self.store_profiles();
+    }
+
+    pub fn delete_profile(&mut self, idx: usize) {
+        if idx >= self.config.profiles.len() {
+            return;
+        }
+        self.config.profiles.remove(idx);
+        let len = self.config.profiles.len();
+        self.selected_profile = len.checked_sub(1).map(|last| idx.min(last));
+        if len == 0 {
+            self.focus_on_profiles = false;
+        }
+        self.store_profiles();
+    }
+
+    /// Moves a profile up or down, keeping pinned profiles above unpinned ones.
+    pub fn move_profile(&mut self, idx: usize, up: bool) {
+        let profiles = &mut self.config.profiles;
+        let Some(target) = (if up { idx.checked_sub(1) } else { Some(idx + 1) }) else { return };
+        if target >= profiles.len() || profiles[idx].pinned != profiles[target].pinned {
+            return;
+        }
+        profiles.swap(idx, target);
+        self.selected_profile = Some(target);
+        self.store_profiles();
+    }
+
+    pub fn toggle_pinned(&mut self, idx: usize) {
+        if idx >= self.config.profiles.len() {
+            return;
+        }
+        let mut profile = self.config.profiles.remove(idx);
+        profile.pinned = !profile.pinned;
+        let at = self.config.profiles.iter().take_while(|p| p.pinned).count();
+        self.config.profiles.insert(at, profile);
+        self.selected_profile = Some(at);
+        self.store_profiles();
+    }
+
+    pub fn toggle_auto_connect(&mut self, idx: usize) {
+        let enable = self.config.profiles.get(idx).is_some_and(|p| !p.auto_connect);
+        for (i, p) in self.config.profiles.iter_mut().enumerate() {
+            p.auto_connect = enable && i == idx;
+        }
+        self.store_profiles();
+    }
+
+    fn store_profiles(&mut self) {
+        self.profiles = self.config.profiles.clone();
+        let _ = self.config.save();
+    }
+}
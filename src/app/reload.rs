@@ -0,0 +1,69 @@
+use crate::app::logic::apply_filters;
+use crate::app::App;
+use crate::keymap::Keymap;
+
+impl App {
+    /// Re-reads the settings file and applies what can change while running:
+    /// theme, keybindings, sounds, notification prefs, ignores and filters.
+    pub fn reload_config(&mut self) {
+        let cfg = match self.config.reload() {
+            Ok(Some(cfg)) => cfg,
+            Ok(None) => return,
+            Err(e) => {
+                self.push_system_msg(&format!("Settings not reloaded: {e:#}"));
+                return;
+            }
+        };
+        // Our own saves trigger the watcher too; skip when nothing changed.
+        if toml::to_string(&cfg).ok() == toml::to_string(&self.config).ok() {
+            return;
+        }
+
+        let (keymap, key_errors) = Keymap::from_config(&cfg.keys);
+        self.keymap = keymap;
+        self.filters = Self::compile_filters(&cfg);
+        self.audio.configure(cfg.sounds.clone());
+        self.profiles = cfg.profiles.clone();
+        self.config = cfg;
+        self.config_error = None;
+        self.apply_profile_prefs();
+        self.refilter_messages();
+
+        self.push_system_msg("Settings reloaded");
+        for e in key_errors {
+            self.push_system_msg(&e);
+        }
+    }
+
+    /// Picks up per-profile notification settings for every open session.
+    fn apply_profile_prefs(&mut self) {
+        let port = self.config.default_port();
+        let profiles = &self.config.profiles;
+        let find = |server: &str, username: &str| {
+            profiles.iter().find(|p| p.matches(server, username, port))
+        };
+        if let Some(p) = find(&self.chat.server, &self.chat.username) {
+            self.notif_prefs = p.notifications.clone();
+            self.show_join_part = p.show_join_part;
+        }
+        for session in &mut self.parked {
+            if let Some(p) = find(&session.chat.server, &session.chat.username) {
+                session.notif_prefs = p.notifications.clone();
+                session.show_join_part = p.show_join_part;
+            }
+        }
+    }
+
+    /// Re-evaluates hide/dim state of loaded messages against the current
+    /// ignore list and filters.
+    pub fn refilter_messages(&mut self) {
+        let (config, filters) = (&self.config, &self.filters);
+        let chats = std::iter::once(&mut self.chat).chain(self.parked.iter_mut().map(|s| &mut s.chat));
+        for chat in chats {
+            let own = chat.username.clone();
+            for msg in chat.messages.iter_mut().filter(|m| !m.is_system && m.from != own) {
+                apply_filters(config, filters, msg);
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+use crate::app::admin::AdminConsole;
+use crate::app::commands::device_key_lines;
+use crate::app::incoming::MsgContext;
+use crate::app::{App, ChatState, Screen};
+use crate::config::NotificationPrefs;
+use crate::crypto::LockedKey;
+use crate::net::{NetCommand, NetEvent};
+
+/// Per-server state of a session that is connected but not currently shown.
+#[derive(Default)]
+pub struct Session {
+    pub id: u64,
+    pub chat: ChatState,
+    pub admin: AdminConsole,
+    pub net_cmd_tx: Option<mpsc::UnboundedSender<NetCommand>>,
+    pub notif_prefs: NotificationPrefs,
+    pub show_join_part: bool,
+    pub is_admin: bool,
+    pub signing_key: Option<Arc<LockedKey>>,
+    pub pubkey_b64: Option<String>,
+}
+
+pub struct SessionTab {
+    pub id: u64,
+    pub server: String,
+    pub unread: u32,
+    pub active: bool,
+    pub online: bool,
+}
+
+impl App {
+    /// Swaps the active session's state with `session`.
+    fn swap_active(&mut self, session: &mut Session) {
+        std::mem::swap(&mut self.session_id, &mut session.id);
+        std::mem::swap(&mut self.chat, &mut session.chat);
+        std::mem::swap(&mut self.admin, &mut session.admin);
+        std::mem::swap(&mut self.net_cmd_tx, &mut session.net_cmd_tx);
+        std::mem::swap(&mut self.notif_prefs, &mut session.notif_prefs);
+        std::mem::swap(&mut self.show_join_part, &mut session.show_join_part);
+        std::mem::swap(&mut self.is_admin, &mut session.is_admin);
+        std::mem::swap(&mut self.signing_key, &mut session.signing_key);
+        std::mem::swap(&mut self.pubkey_b64, &mut session.pubkey_b64);
+    }
+
+    /// Starts a fresh active session, keeping the previous one if it is still connected.
+    pub fn park_active_session(&mut self) {
+        self.next_session_id += 1;
+        let mut fresh = Session { id: self.next_session_id, show_join_part: true, ..Default::default() };
+        self.swap_active(&mut fresh);
+        if fresh.chat.authenticated && fresh.net_cmd_tx.is_some() {
+            self.parked.push(fresh);
+        }
+    }
+
+    pub fn switch_session(&mut self, id: u64) -> bool {
+        let Some(idx) = self.parked.iter().position(|s| s.id == id) else {
+            return false;
+        };
+        let mut session = self.parked.remove(idx);
+        self.swap_active(&mut session);
+        if session.chat.authenticated {
+            self.parked.push(session);
+            self.parked.sort_by_key(|s| s.id);
+        }
+        self.chat.unread = 0;
+        self.clear_key_entry();
+        self.screen = Screen::Chat;
+        true
+    }
+
+    /// Disconnects the active session and shows the next one, or the connect screen.
+    pub fn close_active_session(&mut self) {
+        self.net_cmd_tx = None;
+        self.chat.authenticated = false;
+        let next = self.parked.first().map(|s| s.id);
+        match next {
+            Some(id) => {
+                self.switch_session(id);
+            }
+            None => {
+                self.park_active_session();
+                self.screen = Screen::Connect;
+            }
+        }
+    }
+
+    /// Routes a network event to the session it belongs to. Returns true to quit.
+    pub fn handle_session_event(&mut self, id: u64, evt: NetEvent) -> bool {
+        if id == self.session_id {
+            return self.handle_net_event(evt);
+        }
+        let Some(idx) = self.parked.iter().position(|s| s.id == id) else {
+            return false;
+        };
+
+        // Only the session's own state changes here; alerts are raised below.
+        let session = &mut self.parked[idx];
+        let ctx = MsgContext {
+            config: &self.config,
+            filters: &self.filters,
+            show_join_part: session.show_join_part,
+            focused: self.terminal_focused,
+            background: true,
+        };
+        let chat = &mut session.chat;
+        let mut alerts = Vec::new();
+        match evt {
+            // A parked session is already logged in; handshake events do not apply.
+            NetEvent::Connected | NetEvent::AuthOk { .. } | NetEvent::AuthFail { .. } => {}
+            NetEvent::Message(msg) => alerts = chat.apply_server_msg(*msg, &ctx),
+            NetEvent::DeviceKeys(keys) => {
+                chat.device_keys = keys;
+                let own = session.pubkey_b64.as_deref().unwrap_or_default();
+                for line in device_key_lines(chat, own) {
+                    chat.push_system(&line, &ctx);
+                }
+            }
+            NetEvent::AdminResponse { action, data, req_id } => {
+                let notice = session.admin.record_in_background(action, data, req_id);
+                chat.push_message(notice, &ctx);
+            }
+            NetEvent::Typing { user, active } => chat.set_typing(user, active),
+            NetEvent::Notice(text) => chat.push_system(&text, &ctx),
+            NetEvent::Error(e) => {
+                chat.push_system(&format!("Connection error: {e}"), &ctx);
+                session.net_cmd_tx = None;
+            }
+            NetEvent::Disconnected => {
+                chat.push_system("Disconnected from server", &ctx);
+                session.net_cmd_tx = None;
+            }
+        }
+        let (prefs, username) = (session.notif_prefs.clone(), session.chat.username.clone());
+        self.raise_alerts(alerts, &prefs, &username);
+        false
+    }
+
+    pub fn session_tabs(&self) -> Vec<SessionTab> {
+        let mut tabs: Vec<SessionTab> = self.parked.iter()
+            .map(|s| SessionTab {
+                id: s.id,
+                server: s.chat.server.clone(),
+                unread: s.chat.unread,
+                active: false,
+                online: s.net_cmd_tx.is_some(),
+            })
+            .collect();
+        if self.chat.authenticated || !self.parked.is_empty() {
+            tabs.push(SessionTab {
+                id: self.session_id,
+                server: self.chat.server.clone(),
+                unread: 0,
+                active: true,
+                online: self.net_cmd_tx.is_some(),
+            });
+        }
+        tabs.sort_by_key(|t| t.id);
+        tabs
+    }
+}
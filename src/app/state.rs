@@ -1,10 +1,18 @@
+use chrono::{DateTime, Local};
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::mpsc;
-use ed25519_dalek::SigningKey;
 
-use crate::config::Config;
+use crate::app::admin::AdminConsole;
+use crate::app::session::Session;
+use crate::crypto::LockedKey;
+use crate::config::{Config, FilterAction, NotificationPrefs, Overrides};
 use crate::events::AppEvent;
-use crate::net::{NetCommand, NetEvent};
+use crate::keymap::Keymap;
+use crate::net::{AuthFailure, NetCommand, NetEvent};
+use crate::notify::{AudioPlayer, DesktopNotification};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Screen {
@@ -14,16 +22,101 @@ pub enum Screen {
     Auth,       
     Enroll,     
     Chat,       
+    Filters,
+    Admin,
     Error(String),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ChatMessage {
+    pub id: Option<String>,
+    pub reply_to: Option<String>,
     pub from: String,
     pub text: String,
-    pub timestamp: String,
+    pub timestamp: DateTime<Local>,
     pub is_system: bool,
     pub is_admin: bool,
+    pub is_direct: bool,
+    pub edited: bool,
+    pub deleted: bool,
+    pub hidden: bool,
+    pub dimmed: bool,
+    pub reactions: BTreeMap<String, Vec<String>>,
+}
+
+impl ChatMessage {
+    /// The text to show or copy anywhere outside the message's own line; a
+    /// hidden message keeps its text for re-filtering but never shows it.
+    pub fn visible_text(&self) -> &str {
+        if self.hidden { "⋯ hidden message" } else { &self.text }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum PresenceState {
+    #[default]
+    Online,
+    Busy,
+    Away,
+    Idle,
+}
+
+impl PresenceState {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "online" | "active" | "back" => Some(Self::Online),
+            "busy" | "dnd" => Some(Self::Busy),
+            "away" => Some(Self::Away),
+            "idle" => Some(Self::Idle),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Online => "online",
+            Self::Busy => "busy",
+            Self::Away => "away",
+            Self::Idle => "idle",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Presence {
+    pub state: PresenceState,
+    pub status: Option<String>,
+    pub since: Option<DateTime<Local>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserAction {
+    Message,
+    Mention,
+    Ignore,
+    Verify,
+    Kick,
+    Ban,
+}
+
+impl UserAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Message => "Send direct message",
+            Self::Mention => "Mention in input",
+            Self::Ignore => "Ignore / unignore",
+            Self::Verify => "Mark key verified / unverified",
+            Self::Kick => "Kick (admin)",
+            Self::Ban => "Ban (admin)",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UserPopup {
+    pub name: String,
+    pub actions: Vec<UserAction>,
+    pub cursor: usize,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -33,6 +126,23 @@ pub struct ConnectForm {
     pub manual_key: String,
     pub focused_field: usize,
     pub insecure: bool,
+    pub key_file: Option<std::path::PathBuf>,
+    pub invite_code: Option<String>,
+    /// Passphrase being typed to unlock a pasted key backup.
+    pub backup_passphrase: Option<String>,
+    pub backup_error: Option<String>,
+}
+
+pub const PROFILE_TEXT_FIELDS: [&str; 6] = ["NAME", "SERVER", "PORT", "USERNAME", "KEY FILE", "NOTES"];
+
+#[derive(Debug, Clone, Default)]
+pub struct ProfileEditor {
+    pub index: Option<usize>,
+    pub fields: [String; 6],
+    pub insecure: bool,
+    pub auto_connect: bool,
+    pub focused: usize,
+    pub error: Option<String>,
 }
 
 #[derive(Default)]
@@ -47,7 +157,29 @@ pub struct ChatState {
     pub show_help: bool,
     pub user_count: u32,
     pub focus_users: bool,
-    pub admin_response: Option<String>,
+    pub unread_marker: Option<usize>,
+    pub marker_pending: bool,
+    pub new_below: usize,
+    pub msg_view: Cell<(usize, usize)>,
+    pub detail: Option<usize>,
+    pub selected: Option<usize>,
+    pub flash: Option<(String, u64)>,
+    pub replying_to: Option<String>,
+    pub editing: Option<String>,
+    /// Id of an own message waiting for y/N before it is deleted.
+    pub confirm_delete: Option<String>,
+    pub reaction_picker: Option<(usize, usize)>,
+    pub typing: HashMap<String, Instant>,
+    pub typing_sent: Option<Instant>,
+    pub last_input_edit: Option<Instant>,
+    pub presence: HashMap<String, Presence>,
+    pub users_known: bool,
+    pub user_cursor: usize,
+    pub user_popup: Option<UserPopup>,
+    pub user_keys: HashMap<String, String>,
+    pub authenticated: bool,
+    pub unread: u32,
+    pub device_keys: Vec<crate::net::DeviceKey>,
 }
 
 impl ChatState {
@@ -56,29 +188,113 @@ impl ChatState {
     }
     pub fn scroll_down(&mut self, n: usize) {
         self.scroll_offset = self.scroll_offset.saturating_sub(n);
+        if self.scroll_offset == 0 {
+            self.new_below = 0;
+        }
     }
     pub fn scroll_to_bottom(&mut self) {
         self.scroll_offset = 0;
+        self.new_below = 0;
+    }
+    pub fn scroll_to_row(&mut self, row: usize, total_rows: usize) {
+        let (_, height) = self.msg_view.get();
+        self.scroll_offset = total_rows.saturating_sub(row + height);
+        if self.scroll_offset == 0 {
+            self.new_below = 0;
+        }
+    }
+    pub fn last_own_editable(&self) -> Option<usize> {
+        self.messages.iter().rposition(|m| {
+            m.from == self.username && m.id.is_some() && !m.deleted && !m.is_system
+        })
+    }
+    /// Whether typed keys go to the message input rather than a popup or list.
+    pub fn input_focused(&self) -> bool {
+        self.selected.is_none() && !self.focus_users && self.user_popup.is_none()
+            && self.reaction_picker.is_none() && self.confirm_delete.is_none()
+    }
+    /// Whether Up on an empty input at the bottom of the view would edit a message.
+    pub fn can_edit_last(&self) -> bool {
+        self.input_focused() && self.input.is_empty() && self.editing.is_none()
+            && self.scroll_offset == 0 && self.last_own_editable().is_some()
+    }
+    pub fn find_by_id(&self, id: &str) -> Option<usize> {
+        self.messages.iter().rposition(|m| m.id.as_deref() == Some(id))
+    }
+    pub fn presence_of(&self, user: &str) -> Presence {
+        self.presence.get(user).cloned().unwrap_or_default()
+    }
+    /// Online users ordered by presence state, then name.
+    pub fn sorted_users(&self) -> Vec<&str> {
+        let mut users: Vec<&str> = self.online_users.iter().map(String::as_str).collect();
+        users.sort_by_key(|u| {
+            let state = self.presence.get(*u).map(|p| p.state).unwrap_or_default();
+            (state, u.to_lowercase())
+        });
+        users
+    }
+    pub fn cursor_user(&self) -> Option<&str> {
+        let users = self.sorted_users();
+        users.get(self.user_cursor.min(users.len().saturating_sub(1))).copied()
+    }
+    /// Drops the oldest messages beyond `max`, shifting stored indices.
+    pub fn trim_history(&mut self, max: usize) {
+        let Some(excess) = self.messages.len().checked_sub(max).filter(|&n| n > 0) else {
+            return;
+        };
+        self.messages.drain(..excess);
+        let shift = |i: Option<usize>| i.and_then(|i| i.checked_sub(excess));
+        self.unread_marker = shift(self.unread_marker);
+        self.detail = shift(self.detail);
+        self.selected = shift(self.selected);
+        self.reaction_picker = self.reaction_picker
+            .and_then(|(i, c)| i.checked_sub(excess).map(|i| (i, c)));
+    }
+    /// Inserts text at the input cursor.
+    pub fn insert_input(&mut self, text: &str) {
+        let byte_pos = self.input.char_indices()
+            .nth(self.input_cursor)
+            .map(|(i, _)| i)
+            .unwrap_or(self.input.len());
+        self.input.insert_str(byte_pos, text);
+        self.input_cursor += text.chars().count();
     }
     pub fn scroll_to_top(&mut self) {
         self.scroll_offset = usize::MAX;
     }
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct FiltersForm {
+    pub cursor: usize,
+    pub input: Option<String>,
+    pub action: FilterAction,
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct EnrollForm {
     pub invite_code: String,
+    pub username: String,
+    /// 0 = invite code, 1 = username.
+    pub focused: usize,
+    pub failure: Option<AuthFailure>,
 }
 
 pub struct App {
     pub screen: Screen,
     pub config: Config,
+    pub config_error: Option<String>,
     pub profiles: Vec<crate::config::ServerProfile>,
     pub selected_profile: Option<usize>,
+    pub profile_editor: Option<ProfileEditor>,
+    pub profile_delete_armed: Option<usize>,
     pub connect_form: ConnectForm,
     pub enroll_form: EnrollForm,
+    pub filters_form: FiltersForm,
     pub chat: ChatState,
-    pub signing_key: Option<Arc<SigningKey>>,
+    pub admin: AdminConsole,
+    pub signing_key: Option<Arc<LockedKey>>,
     pub pubkey_b64: Option<String>,
     pub is_new_key: bool,
     pub tick_count: u64,
@@ -87,32 +303,60 @@ pub struct App {
     pub focus_on_profiles: bool,
 
     pub net_cmd_tx: Option<mpsc::UnboundedSender<NetCommand>>,
-    pub net_event_rx: Option<mpsc::UnboundedReceiver<NetEvent>>,
+    pub net_event_tx: mpsc::UnboundedSender<(u64, NetEvent)>,
+    pub net_event_rx: Option<mpsc::UnboundedReceiver<(u64, NetEvent)>>,
+    pub session_id: u64,
+    pub next_session_id: u64,
+    pub parked: Vec<Session>,
     pub app_event_tx: Option<mpsc::UnboundedSender<AppEvent>>,
+    pub notifier: Option<mpsc::UnboundedSender<DesktopNotification>>,
+    pub audio: AudioPlayer,
 
     pub terminal_focused: bool,
     pub unread_count: u32,
-    pub notifications_muted: bool,
+    pub notif_prefs: NotificationPrefs,
+    pub show_join_part: bool,
+    pub last_key_at: Instant,
+    pub auto_idle: bool,
+    pub is_admin: bool,
+    pub filters: Vec<(regex::Regex, FilterAction)>,
+    pub keymap: Keymap,
+    pub confirm_quit: bool,
 }
 
 impl App {
-    pub fn new() -> Self {
-        let config = Config::load();
+    pub fn new(overrides: Overrides) -> Self {
+        let (config, mut config_error) = Config::load(overrides);
+        let (keymap, key_errors) = Keymap::from_config(&config.keys);
+        if !key_errors.is_empty() {
+            let mut errors = config_error.into_iter().collect::<Vec<_>>();
+            errors.extend(key_errors);
+            config_error = Some(errors.join("; "));
+        }
         let profiles = config.profiles.clone();
+        let audio = AudioPlayer::spawn(config.sounds.clone());
+        let filters = Self::compile_filters(&config);
+        let (net_event_tx, net_event_rx) = mpsc::unbounded_channel();
         let connect_form = ConnectForm {
             server: config.last_server.clone().unwrap_or_default(),
             username: config.last_username.clone().unwrap_or_default(),
             ..Default::default()
         };
+        let invite = config.overrides.invite.clone();
 
-        Self {
+        let mut app = Self {
             screen: Screen::Splash,
             config,
+            config_error,
             profiles,
             selected_profile: None,
+            profile_editor: None,
+            profile_delete_armed: None,
             connect_form,
             enroll_form: EnrollForm::default(),
+            filters_form: FiltersForm::default(),
             chat: ChatState::default(),
+            admin: AdminConsole::default(),
             signing_key: None,
             pubkey_b64: None,
             is_new_key: false,
@@ -121,11 +365,35 @@ impl App {
             status_msg: None,
             focus_on_profiles: false,
             net_cmd_tx: None,
-            net_event_rx: None,
+            net_event_tx,
+            net_event_rx: Some(net_event_rx),
+            session_id: 0,
+            next_session_id: 0,
+            parked: Vec::new(),
             app_event_tx: None,
+            notifier: None,
+            audio,
             terminal_focused: true,
             unread_count: 0,
-            notifications_muted: false,
+            notif_prefs: NotificationPrefs::default(),
+            show_join_part: true,
+            last_key_at: Instant::now(),
+            auto_idle: false,
+            is_admin: false,
+            filters,
+            keymap,
+            confirm_quit: false,
+        };
+        if let Some(invite) = invite {
+            app.apply_invite(invite);
         }
+        app
+    }
+
+    /// Compiles the configured content filters, skipping invalid patterns.
+    pub fn compile_filters(config: &Config) -> Vec<(regex::Regex, FilterAction)> {
+        config.filters.iter()
+            .filter_map(|f| regex::Regex::new(&f.pattern).ok().map(|re| (re, f.action)))
+            .collect()
     }
 }
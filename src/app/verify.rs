@@ -0,0 +1,62 @@
+use crate::app::{App, ChatState};
+use crate::config::Config;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trust {
+    /// The server has not shared this user's key.
+    Unknown,
+    Unverified,
+    Verified,
+    /// Marked verified, but the key seen now is a different one.
+    Changed,
+}
+
+impl ChatState {
+    fn contact_id(&self, name: &str) -> String {
+        format!("{name}@{}", self.server)
+    }
+
+    pub fn trust_of(&self, config: &Config, name: &str) -> Trust {
+        let Some(key) = self.user_keys.get(name) else {
+            return Trust::Unknown;
+        };
+        match config.verified.get(&self.contact_id(name)) {
+            Some(verified) if verified == key => Trust::Verified,
+            Some(_) => Trust::Changed,
+            None => Trust::Unverified,
+        }
+    }
+}
+
+impl App {
+    pub fn trust_of(&self, name: &str) -> Trust {
+        self.chat.trust_of(&self.config, name)
+    }
+
+    /// Safety number shared with `name`, if both keys are known.
+    pub fn safety_number(&self, name: &str) -> Option<String> {
+        let ours = self.pubkey_b64.as_deref()?;
+        let theirs = self.chat.user_keys.get(name)?;
+        crate::crypto::safety_number(ours, theirs)
+    }
+
+    pub fn toggle_verified(&mut self, name: &str) {
+        let id = self.chat.contact_id(name);
+        match self.trust_of(name) {
+            Trust::Unknown => {
+                self.push_system_msg(&format!("The server has not shared {name}'s key; nothing to verify."));
+                return;
+            }
+            Trust::Verified => {
+                self.config.verified.remove(&id);
+                self.push_system_msg(&format!("{name} is no longer marked verified."));
+            }
+            Trust::Unverified | Trust::Changed => {
+                let key = self.chat.user_keys.get(name).cloned().unwrap_or_default();
+                self.config.verified.insert(id, key);
+                self.push_system_msg(&format!("{name} marked verified. Their current key will be trusted."));
+            }
+        }
+        let _ = self.config.save();
+    }
+}
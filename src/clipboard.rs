@@ -0,0 +1,14 @@
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use std::io::Write;
+
+/// Copies via OSC 52 so it also works over SSH. tmux needs DCS passthrough.
+pub fn copy_osc52(text: &str) -> bool {
+    let payload = B64.encode(text.as_bytes());
+    let seq = if std::env::var_os("TMUX").is_some() {
+        format!("\x1bPtmux;\x1b\x1b]52;c;{payload}\x07\x1b\\")
+    } else {
+        format!("\x1b]52;c;{payload}\x07")
+    };
+    let mut out = std::io::stdout().lock();
+    out.write_all(seq.as_bytes()).and_then(|_| out.flush()).is_ok()
+}
@@ -1,21 +1,464 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveTime};
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
+use std::collections::BTreeMap;
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::invite::Invite;
+use crate::keymap::KeyConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn parse(s: &str) -> Option<Self> {
+        let (start, end) = s.split_once('-')?;
+        let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?;
+        let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").ok()?;
+        Some(Self { start, end })
+    }
+
+    /// Windows where `start > end` wrap past midnight, e.g. 19:00–08:00.
+    pub fn contains(&self, t: NaiveTime) -> bool {
+        if self.start <= self.end {
+            t >= self.start && t < self.end
+        } else {
+            t >= self.start || t < self.end
+        }
+    }
+}
+
+impl std::fmt::Display for QuietHours {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}–{}", self.start.format("%H:%M"), self.end.format("%H:%M"))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationPrefs {
+    pub muted: bool,
+    pub sound: bool,
+    pub desktop: bool,
+    pub title_counter: bool,
+    pub muted_senders: Vec<String>,
+    pub quiet_hours: Option<QuietHours>,
+}
+
+impl Default for NotificationPrefs {
+    fn default() -> Self {
+        Self {
+            muted: false,
+            sound: true,
+            desktop: true,
+            title_counter: true,
+            muted_senders: Vec::new(),
+            quiet_hours: None,
+        }
+    }
+}
+
+impl NotificationPrefs {
+    pub fn is_quiet(&self, now: NaiveTime) -> bool {
+        self.quiet_hours.is_some_and(|q| q.contains(now))
+    }
+
+    pub fn allows(&self, from: &str, now: NaiveTime) -> bool {
+        !self.muted
+            && !self.muted_senders.iter().any(|s| s == from)
+            && !self.is_quiet(now)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SoundPrefs {
+    pub volume: f32,
+    pub message: Option<PathBuf>,
+    pub mention: Option<PathBuf>,
+    pub dm: Option<PathBuf>,
+    pub join: Option<PathBuf>,
+}
+
+impl Default for SoundPrefs {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            message: None,
+            mention: None,
+            dm: None,
+            join: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterAction {
+    #[default]
+    Hide,
+    Dim,
+}
+
+impl FilterAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hide => "hide",
+            Self::Dim => "dim",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentFilter {
+    pub pattern: String,
+    #[serde(default)]
+    pub action: FilterAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ClockStyle {
+    #[default]
+    #[serde(rename = "24h")]
+    H24,
+    #[serde(rename = "12h")]
+    H12,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimestampFormat {
+    pub clock: ClockStyle,
+    pub seconds: bool,
+    pub relative: bool,
+}
+
+impl TimestampFormat {
+    pub fn format(&self, ts: DateTime<Local>, now: DateTime<Local>) -> String {
+        if self.relative {
+            let age = now.signed_duration_since(ts);
+            if age.num_seconds() < 60 {
+                return "now".into();
+            } else if age.num_minutes() < 60 {
+                return format!("{}m ago", age.num_minutes());
+            } else if age.num_hours() < 24 {
+                return format!("{}h ago", age.num_hours());
+            }
+        }
+        let pattern = match (self.clock, self.seconds) {
+            (ClockStyle::H24, false) => "%H:%M",
+            (ClockStyle::H24, true) => "%H:%M:%S",
+            (ClockStyle::H12, false) => "%I:%M %p",
+            (ClockStyle::H12, true) => "%I:%M:%S %p",
+        };
+        ts.format(pattern).to_string()
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerProfile {
+    #[serde(default)]
+    pub name: String,
     pub server: String,
+    #[serde(default)]
+    pub port: Option<u16>,
     pub username: String,
+    #[serde(default)]
+    pub insecure: bool,
+    #[serde(default)]
+    pub key_file: Option<PathBuf>,
+    #[serde(default)]
+    pub auto_connect: bool,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub notifications: NotificationPrefs,
+    #[serde(default = "default_true")]
+    pub show_join_part: bool,
+}
+
+impl ServerProfile {
+    pub fn new(server: &str, username: &str) -> Self {
+        Self {
+            name: String::new(),
+            server: server.to_string(),
+            port: None,
+            username: username.to_string(),
+            insecure: false,
+            key_file: None,
+            auto_connect: false,
+            pinned: false,
+            notes: String::new(),
+            notifications: NotificationPrefs::default(),
+            show_join_part: true,
+        }
+    }
+
+    /// `host:port` to dial, applying the explicit port or the default one.
+    pub fn address(&self, default_port: u16) -> String {
+        match self.port {
+            Some(port) => format!("{}:{port}", self.server.rsplit_once(':').map_or(self.server.as_str(), |(h, _)| h)),
+            None if self.server.contains(':') => self.server.clone(),
+            None => format!("{}:{default_port}", self.server),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        if self.name.is_empty() {
+            format!("{}@{}", self.username, self.server)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// Whether the user has edited this profile beyond what auto-saving fills in.
+    pub fn is_customized(&self) -> bool {
+        !self.name.is_empty() || !self.notes.is_empty() || self.key_file.is_some() || self.auto_connect
+    }
+
+    pub fn matches(&self, address: &str, username: &str, default_port: u16) -> bool {
+        self.username == username && self.address(default_port) == address
+    }
+}
+
+pub const DEFAULT_PORT: u16 = 7000;
+pub const CONFIG_VERSION: u32 = 2;
+const MAX_RECENT_PROFILES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Default,
+    Mono,
+}
+
+impl Theme {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "default" | "color" => Some(Self::Default),
+            "mono" | "monochrome" => Some(Self::Mono),
+            _ => None,
+        }
+    }
+}
+
+/// Settings given on the command line or in the environment. They win over
+/// the file but are never written back to it.
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    pub config_path: Option<PathBuf>,
+    pub theme: Option<Theme>,
+    pub default_port: Option<u16>,
+    pub history_size: Option<usize>,
+    /// Invite link given on the command line; pre-fills the connect form.
+    pub invite: Option<Invite>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+impl Overrides {
+    /// Reads `TTYCHAT_*` variables, then `--flag value` arguments on top.
+    pub fn from_env_and_args(args: &[String]) -> std::result::Result<Self, String> {
+        let mut o = Self::default();
+        let env = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        if let Some(v) = env("TTYCHAT_CONFIG") {
+            o.set("--config", &v)?;
+        }
+        if let Some(v) = env("TTYCHAT_THEME") {
+            o.set("--theme", &v)?;
+        }
+        if let Some(v) = env("TTYCHAT_PORT") {
+            o.set("--port", &v)?;
+        }
+        if let Some(v) = env("TTYCHAT_HISTORY") {
+            o.set("--history", &v)?;
+        }
+
+        let mut it = args.iter();
+        while let Some(flag) = it.next() {
+            if flag.starts_with("ttychat://") {
+                o.invite = Some(Invite::parse(flag).ok_or(format!("invalid invite link: {flag}"))?);
+                continue;
+            }
+            let (flag, value) = match flag.split_once('=') {
+                Some((f, v)) => (f, v.to_string()),
+                None => (flag.as_str(), it.next().cloned().ok_or(format!("{flag} needs a value"))?),
+            };
+            o.set(flag, &value)?;
+        }
+        Ok(o)
+    }
+
+    fn set(&mut self, flag: &str, value: &str) -> std::result::Result<(), String> {
+        match flag {
+            "--config" => self.config_path = Some(PathBuf::from(value)),
+            "--theme" => {
+                self.theme = Some(Theme::parse(value).ok_or(format!("unknown theme: {value}"))?);
+            }
+            "--port" => {
+                self.default_port = Some(value.parse().map_err(|_| format!("invalid port: {value}"))?);
+            }
+            "--history" => {
+                self.history_size = Some(value.parse().map_err(|_| format!("invalid history size: {value}"))?);
+            }
+            _ => return Err(format!("unknown option: {flag}")),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
-    pub profiles: Vec<ServerProfile>,
+    #[serde(default = "legacy_version")]
+    pub version: u32,
+    #[serde(default)]
+    pub theme: Theme,
+    #[serde(default = "default_port")]
+    pub default_port: u16,
+    #[serde(default = "default_history_size")]
+    pub history_size: usize,
     pub last_server: Option<String>,
     pub last_username: Option<String>,
+    #[serde(default = "default_true")]
+    pub send_typing: bool,
+    #[serde(default = "default_idle_after")]
+    pub idle_after_mins: u32,
+    #[serde(default)]
+    pub ignored: Vec<String>,
+    #[serde(default = "default_true")]
+    pub hidden_placeholder: bool,
+    #[serde(default)]
+    pub timestamp: TimestampFormat,
+    #[serde(default)]
+    pub notifications: NotificationPrefs,
+    #[serde(default)]
+    pub sounds: SoundPrefs,
+    /// Contacts whose key was checked out of band: `user@host:port` → public key.
+    #[serde(default)]
+    pub verified: BTreeMap<String, String>,
+    #[serde(default)]
+    pub keys: KeyConfig,
+    #[serde(default)]
+    pub filters: Vec<ContentFilter>,
+    #[serde(default)]
+    pub profiles: Vec<ServerProfile>,
+    #[serde(skip)]
+    pub path: PathBuf,
+    #[serde(skip)]
+    pub overrides: Overrides,
+}
+
+fn legacy_version() -> u32 {
+    1
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+fn default_history_size() -> usize {
+    1000
+}
+
+fn default_idle_after() -> u32 {
+    10
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            theme: Theme::default(),
+            default_port: DEFAULT_PORT,
+            history_size: default_history_size(),
+            last_server: None,
+            last_username: None,
+            send_typing: true,
+            idle_after_mins: default_idle_after(),
+            ignored: Vec::new(),
+            hidden_placeholder: true,
+            timestamp: TimestampFormat::default(),
+            notifications: NotificationPrefs::default(),
+            sounds: SoundPrefs::default(),
+            verified: BTreeMap::new(),
+            keys: KeyConfig::default(),
+            filters: Vec::new(),
+            profiles: Vec::new(),
+            path: Self::config_path(),
+            overrides: Overrides::default(),
+        }
+    }
+}
+
+/// Schema migrations, applied to the raw document in order. Entry `i`
+/// upgrades version `i + 1` to `i + 2`.
+const MIGRATIONS: &[fn(&mut serde_json::Value)] = &[
+    // v1 (config.json) -> v2 (config.toml): TOML has no null, drop unset keys.
+    strip_nulls,
+];
+
+fn strip_nulls(v: &mut serde_json::Value) {
+    match v {
+        serde_json::Value::Object(map) => {
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
 }
 
 impl Config {
+    pub fn theme(&self) -> Theme {
+        self.overrides.theme.unwrap_or(self.theme)
+    }
+
+    pub fn default_port(&self) -> u16 {
+        self.overrides.default_port.unwrap_or(self.default_port)
+    }
+
+    pub fn history_size(&self) -> usize {
+        self.overrides.history_size.unwrap_or(self.history_size).max(1)
+    }
+
+    /// Returns the profile for a successful login, creating it after the pinned
+    /// profiles if needed and evicting the oldest unpinned ones beyond the limit.
+    pub fn remember_profile(&mut self, address: &str, username: &str) -> ServerProfile {
+        let port = self.default_port();
+        let existing = self.profiles.iter().position(|p| p.matches(address, username, port));
+        if let Some(i) = existing.filter(|&i| self.profiles[i].pinned) {
+            return self.profiles[i].clone();
+        }
+        // Most recently used first among the unpinned profiles.
+        let profile = match existing {
+            Some(i) => self.profiles.remove(i),
+            None => {
+                let mut profile = ServerProfile::new(address, username);
+                profile.notifications = self.notifications.clone();
+                profile
+            }
+        };
+        let at = self.profiles.iter().take_while(|p| p.pinned).count();
+        self.profiles.insert(at, profile.clone());
+
+        // Only plain auto-saved entries age out; anything the user set up stays.
+        let mut recent = 0;
+        self.profiles.retain(|p| {
+            let evictable = !p.pinned && !p.is_customized();
+            recent += usize::from(evictable);
+            !evictable || recent <= MAX_RECENT_PROFILES
+        });
+        profile
+    }
+
     pub fn config_dir() -> PathBuf {
         dirs::config_dir()
             .unwrap_or_else(|| PathBuf::from("."))
@@ -23,6 +466,10 @@ impl Config {
     }
 
     pub fn config_path() -> PathBuf {
+        Self::config_dir().join("config.toml")
+    }
+
+    fn legacy_path() -> PathBuf {
         Self::config_dir().join("config.json")
     }
 
@@ -43,23 +490,92 @@ impl Config {
         specific_path
     }
 
-    pub fn load() -> Self {
-        let path = Self::config_path();
-        if path.exists() {
-            if let Ok(data) = fs::read_to_string(&path) {
-                if let Ok(cfg) = serde_json::from_str(&data) {
-                    return cfg;
-                }
+    /// Loads the settings file, migrating older versions. A file that cannot be
+    /// read is moved aside and reported instead of being silently replaced.
+    pub fn load(overrides: Overrides) -> (Self, Option<String>) {
+        let path = overrides.config_path.clone().unwrap_or_else(Self::config_path);
+        let legacy = Self::legacy_path();
+        let source = if !path.exists() && overrides.config_path.is_none() && legacy.exists() {
+            legacy.clone()
+        } else {
+            path.clone()
+        };
+
+        let (mut cfg, error) = match Self::read(&source) {
+            Ok(cfg) => (cfg.unwrap_or_default(), None),
+            Err(e) => {
+                let error = match Self::backup(&source) {
+                    Ok(backup) => format!("{e:#}. The file was moved to {} and defaults are in use.", backup.display()),
+                    Err(b) => format!("{e:#}. Could not back it up: {b}"),
+                };
+                (Self::default(), Some(error))
             }
+        };
+        cfg.path = path;
+        cfg.overrides = overrides;
+
+        if error.is_none() && source == legacy && source != cfg.path && cfg.save().is_ok() {
+            let _ = fs::rename(&legacy, legacy.with_extension("json.migrated"));
+        }
+        (cfg, error)
+    }
+
+    /// Re-reads the settings file for a live reload. Unlike `load`, a broken
+    /// file is left in place so it can be fixed; `None` means it is gone.
+    pub fn reload(&self) -> Result<Option<Self>> {
+        let Some(mut cfg) = Self::read(&self.path)? else {
+            return Ok(None);
+        };
+        cfg.path = self.path.clone();
+        cfg.overrides = self.overrides.clone();
+        Ok(Some(cfg))
+    }
+
+    fn read(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
         }
-        Self::default()
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        let mut doc: serde_json::Value = if path.extension().is_some_and(|e| e == "json") {
+            serde_json::from_str(&text)
+                .with_context(|| format!("{} is not valid JSON", path.display()))?
+        } else {
+            toml::from_str(&text)
+                .with_context(|| format!("{} is not valid TOML", path.display()))?
+        };
+
+        let version = doc.get("version").and_then(|v| v.as_u64()).unwrap_or(1) as u32;
+        if version > CONFIG_VERSION {
+            anyhow::bail!("{} was written by a newer ttychat (version {version})", path.display());
+        }
+        for migrate in MIGRATIONS.iter().skip(version.saturating_sub(1) as usize) {
+            migrate(&mut doc);
+        }
+        if let Some(map) = doc.as_object_mut() {
+            map.insert("version".into(), CONFIG_VERSION.into());
+        }
+        let cfg = serde_json::from_value(doc)
+            .with_context(|| format!("{} has invalid settings", path.display()))?;
+        Ok(Some(cfg))
+    }
+
+    fn backup(path: &Path) -> Result<PathBuf> {
+        let stamp = Local::now().format("%Y%m%d-%H%M%S");
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let backup = path.with_file_name(format!("{name}.broken-{stamp}"));
+        fs::rename(path, &backup)?;
+        Ok(backup)
     }
 
     pub fn save(&self) -> Result<()> {
-        let dir = Self::config_dir();
-        fs::create_dir_all(&dir)?;
-        let json = serde_json::to_string_pretty(self)?;
-        fs::write(Self::config_path(), json)?;
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string_pretty(self)?;
+        let tmp = self.path.with_extension("toml.tmp");
+        fs::write(&tmp, text)?;
+        fs::rename(&tmp, &self.path)?;
         Ok(())
     }
 }
@@ -1,17 +1,20 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use base64::{engine::general_purpose::STANDARD as B64, Engine};
 use ed25519_dalek::{SigningKey, VerifyingKey};
-use std::{fs, path::PathBuf};
+use std::{fs, path::{Path, PathBuf}};
+use zeroize::Zeroizing;
 
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 
 pub fn load_or_generate(key_path: &PathBuf) -> Result<(SigningKey, bool)> {
     if key_path.exists() {
-        let raw = fs::read(key_path)?;
-        let seed: [u8; 32] = raw
-            .try_into()
-            .map_err(|_| anyhow::anyhow!("Key file is corrupt (expected 32 bytes)"))?;
+        let raw = Zeroizing::new(fs::read(key_path)?);
+        let seed: Zeroizing<[u8; 32]> = Zeroizing::new(
+            raw.as_slice()
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("Key file is corrupt (expected 32 bytes)"))?,
+        );
         let signing = SigningKey::from_bytes(&seed);
         Ok((signing, false))
     } else {
@@ -20,23 +23,83 @@ pub fn load_or_generate(key_path: &PathBuf) -> Result<(SigningKey, bool)> {
     }
 }
 
-pub fn generate_and_save(key_path: &PathBuf) -> Result<SigningKey> {
-    let mut rng = rand::thread_rng();
-    let signing_key = SigningKey::generate(&mut rng);
-    let seed = signing_key.to_bytes();
+pub fn generate_and_save(key_path: &Path) -> Result<SigningKey> {
+    let signing_key = generate();
+    save_key(key_path, &signing_key)?;
+    Ok(signing_key)
+}
+
+pub fn generate() -> SigningKey {
+    SigningKey::generate(&mut rand::thread_rng())
+}
 
+pub fn save_key(key_path: &Path, signing_key: &SigningKey) -> Result<()> {
     if let Some(parent) = key_path.parent() {
         fs::create_dir_all(parent)?;
     }
-    fs::write(key_path, seed)?;
+    fs::write(key_path, Zeroizing::new(signing_key.to_bytes()))?;
 
     #[cfg(unix)]
     {
         let perms = fs::Permissions::from_mode(0o600);
         fs::set_permissions(key_path, perms)?;
     }
+    Ok(())
+}
 
-    Ok(signing_key)
+/// A signing key on a heap page locked against swapping where the OS allows
+/// it. The seed is wiped before the page is unlocked.
+pub struct LockedKey(Box<SigningKey>);
+
+impl LockedKey {
+    pub fn new(key: SigningKey) -> Self {
+        let boxed = Box::new(key);
+        #[cfg(unix)]
+        // SAFETY: the range is the live allocation behind `boxed`. A failed
+        // mlock (e.g. RLIMIT_MEMLOCK reached) leaves the page unlocked, no worse.
+        unsafe {
+            libc::mlock(std::ptr::from_ref(&*boxed).cast(), std::mem::size_of::<SigningKey>());
+        }
+        Self(boxed)
+    }
+}
+
+impl std::ops::Deref for LockedKey {
+    type Target = SigningKey;
+    fn deref(&self) -> &SigningKey {
+        &self.0
+    }
+}
+
+impl Drop for LockedKey {
+    fn drop(&mut self) {
+        // SigningKey zeroizes itself when dropped; overwrite while still locked.
+        *self.0 = SigningKey::from_bytes(&[0u8; 32]);
+        #[cfg(unix)]
+        // SAFETY: same range that was passed to mlock in `new`.
+        unsafe {
+            libc::munlock(std::ptr::from_ref(&*self.0).cast(), std::mem::size_of::<SigningKey>());
+        }
+    }
+}
+
+/// Stops the process from writing core dumps (and, on Linux, from being
+/// ptrace-attached by other processes of the same user) so a loaded key
+/// cannot end up in a crash dump.
+pub fn disable_core_dumps() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        let limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+        // SAFETY: plain syscalls on a stack value.
+        if unsafe { libc::setrlimit(libc::RLIMIT_CORE, &limit) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        #[cfg(target_os = "linux")]
+        if unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
 }
 
 pub fn sign_nonce(signing_key: &SigningKey, nonce_b64: &str) -> Result<String> {
@@ -46,11 +109,260 @@ pub fn sign_nonce(signing_key: &SigningKey, nonce_b64: &str) -> Result<String> {
     Ok(B64.encode(sig.to_bytes()))
 }
 
+/// Domain tag for key endorsements, so they can never double as a login
+/// signature over a server-chosen nonce.
+const ENDORSE_CONTEXT: &[u8] = b"ttychat-key-endorse-v1";
+
+/// Signs another base64 public key, vouching for it as the same account.
+/// Covers the tag, the username, this key and the endorsed key.
+pub fn endorse(signing_key: &SigningKey, username: &str, pubkey_b64: &str) -> Result<String> {
+    use ed25519_dalek::Signer;
+    let new_pub = B64.decode(pubkey_b64).context("Not a valid base64 public key")?;
+    let mut msg = ENDORSE_CONTEXT.to_vec();
+    msg.extend_from_slice(username.as_bytes());
+    msg.extend_from_slice(signing_key.verifying_key().as_bytes());
+    msg.extend_from_slice(&new_pub);
+    Ok(B64.encode(signing_key.sign(&msg).to_bytes()))
+}
+
 pub fn pubkey_b64(signing_key: &SigningKey) -> String {
     let verifying: VerifyingKey = signing_key.verifying_key();
     B64.encode(verifying.as_bytes())
 }
 
+/// Short SHA-256 fingerprint of a base64 public key, in colon-separated hex groups.
+pub fn fingerprint(pubkey_b64: &str) -> Option<String> {
+    let digest = key_digest(pubkey_b64)?;
+    let groups: Vec<String> = digest[..16].chunks(2).map(hex_pair).collect();
+    Some(groups.join(":"))
+}
+
+fn hex_pair(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// 64 symbols for reading fingerprints aloud, six bits each.
+const SYMBOLS: [(&str, &str); 64] = [
+    ("🐶", "dog"), ("🐱", "cat"), ("🦁", "lion"), ("🐎", "horse"),
+    ("🦄", "unicorn"), ("🐷", "pig"), ("🐘", "elephant"), ("🐰", "rabbit"),
+    ("🐼", "panda"), ("🐓", "rooster"), ("🐧", "penguin"), ("🐢", "turtle"),
+    ("🐟", "fish"), ("🐙", "octopus"), ("🦋", "butterfly"), ("🌷", "flower"),
+    ("🌳", "tree"), ("🌵", "cactus"), ("🍄", "mushroom"), ("🌏", "globe"),
+    ("🌙", "moon"), ("☁️", "cloud"), ("🔥", "fire"), ("🍌", "banana"),
+    ("🍎", "apple"), ("🍓", "strawberry"), ("🌽", "corn"), ("🍕", "pizza"),
+    ("🎂", "cake"), ("❤️", "heart"), ("😀", "smiley"), ("🤖", "robot"),
+    ("🎩", "hat"), ("👓", "glasses"), ("🔧", "spanner"), ("🎅", "santa"),
+    ("👍", "thumbs up"), ("☂️", "umbrella"), ("⌛", "hourglass"), ("⏰", "clock"),
+    ("🎁", "gift"), ("💡", "light bulb"), ("📕", "book"), ("✏️", "pencil"),
+    ("📎", "paperclip"), ("✂️", "scissors"), ("🔒", "lock"), ("🔑", "key"),
+    ("🔨", "hammer"), ("☎️", "telephone"), ("🏁", "flag"), ("🚂", "train"),
+    ("🚲", "bicycle"), ("✈️", "aeroplane"), ("🚀", "rocket"), ("🏆", "trophy"),
+    ("⚽", "ball"), ("🎸", "guitar"), ("🎺", "trumpet"), ("🔔", "bell"),
+    ("⚓", "anchor"), ("🎧", "headphones"), ("📁", "folder"), ("📌", "pin"),
+];
+
+fn key_digest(pubkey_b64: &str) -> Option<[u8; 32]> {
+    use sha2::{Digest, Sha256};
+    let raw = B64.decode(pubkey_b64.trim()).ok()?;
+    Some(Sha256::digest(&raw).into())
+}
+
+/// Seven emoji and their names taken from the key's SHA-256, for comparing by voice.
+pub fn fingerprint_symbols(pubkey_b64: &str) -> Option<Vec<(&'static str, &'static str)>> {
+    let digest = key_digest(pubkey_b64)?;
+    let bits = digest[..6].iter().fold(0u64, |acc, b| (acc << 8) | u64::from(*b));
+    Some((0..7).map(|i| SYMBOLS[((bits >> (42 - 6 * i)) & 0x3f) as usize]).collect())
+}
+
+/// OpenSSH-style "drunken bishop" picture of the key's SHA-256.
+pub fn randomart(pubkey_b64: &str) -> Option<Vec<String>> {
+    const W: usize = 17;
+    const H: usize = 9;
+    const CHARS: &[u8] = b" .o+=*BOX@%&#/^";
+    let digest = key_digest(pubkey_b64)?;
+
+    let mut field = [[0u8; W]; H];
+    let (mut x, mut y) = (W / 2, H / 2);
+    for mut byte in digest {
+        for _ in 0..4 {
+            x = if byte & 1 == 1 { (x + 1).min(W - 1) } else { x.saturating_sub(1) };
+            y = if byte & 2 == 2 { (y + 1).min(H - 1) } else { y.saturating_sub(1) };
+            field[y][x] = field[y][x].saturating_add(1);
+            byte >>= 2;
+        }
+    }
+
+    let mut lines = vec!["+---[ED25519]-----+".to_string()];
+    for (row, cells) in field.iter().enumerate() {
+        let mut line = String::from("|");
+        for (col, &n) in cells.iter().enumerate() {
+            line.push(if (col, row) == (W / 2, H / 2) {
+                'S'
+            } else if (col, row) == (x, y) {
+                'E'
+            } else {
+                CHARS[(n as usize).min(CHARS.len() - 1)] as char
+            });
+        }
+        line.push('|');
+        lines.push(line);
+    }
+    lines.push("+----[SHA256]-----+".to_string());
+    Some(lines)
+}
+
+/// Safety number for a pair of keys: 12 groups of five digits, the same for
+/// both parties, to compare out of band.
+pub fn safety_number(ours_b64: &str, theirs_b64: &str) -> Option<String> {
+    use sha2::{Digest, Sha512};
+    let mut keys = [B64.decode(ours_b64.trim()).ok()?, B64.decode(theirs_b64.trim()).ok()?];
+    keys.sort();
+    let mut hasher = Sha512::new();
+    hasher.update(b"ttychat-safety-number-v1");
+    hasher.update(&keys[0]);
+    hasher.update(&keys[1]);
+    let digest = hasher.finalize();
+
+    let groups: Vec<String> = digest[..60]
+        .chunks(5)
+        .map(|c| c.iter().fold(0u64, |acc, b| (acc << 8) | u64::from(*b)) % 100_000)
+        .map(|n| format!("{n:05}"))
+        .collect();
+    Some(groups.join(" "))
+}
+
+const BACKUP_MAGIC: &[u8; 4] = b"TTYK";
+const BACKUP_VERSION: u8 = 1;
+const BACKUP_BEGIN: &str = "-----BEGIN TTYCHAT KEY BACKUP-----";
+const BACKUP_END: &str = "-----END TTYCHAT KEY BACKUP-----";
+// Argon2id cost: 19 MiB, 2 passes, 1 lane.
+const KDF_MEMORY_KIB: u32 = 19 * 1024;
+const KDF_PASSES: u32 = 2;
+/// Upper bounds on the cost a backup may ask for, so a crafted file cannot
+/// make import allocate or spin without limit.
+const KDF_MAX_MEMORY_KIB: u32 = 256 * 1024;
+const KDF_MAX_PASSES: u32 = 10;
+
+fn backup_cipher(passphrase: &str, salt: &[u8], memory: u32, passes: u32) -> Result<chacha20poly1305::ChaCha20Poly1305> {
+    use argon2::{Algorithm, Argon2, Params, Version};
+    use chacha20poly1305::KeyInit;
+    let params = Params::new(memory, passes, 1, Some(32))
+        .map_err(|e| anyhow::anyhow!("Bad key derivation parameters: {e}"))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| anyhow::anyhow!("Key derivation failed: {e}"))?;
+    Ok(chacha20poly1305::ChaCha20Poly1305::new(key.as_ref().into()))
+}
+
+/// Encrypts the key seed with a passphrase into an armored text block.
+///
+/// Layout: magic, version, Argon2id memory and passes, 16-byte salt, 12-byte
+/// nonce, ChaCha20-Poly1305 ciphertext, then the first 4 bytes of the SHA-256
+/// of everything before it so typos are caught before decryption.
+pub fn export_backup(signing_key: &SigningKey, passphrase: &str, username: &str) -> Result<String> {
+    use chacha20poly1305::aead::{Aead, Payload};
+    use rand::RngCore;
+    use sha2::{Digest, Sha256};
+
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let mut blob = Vec::new();
+    blob.extend_from_slice(BACKUP_MAGIC);
+    blob.push(BACKUP_VERSION);
+    blob.extend_from_slice(&KDF_MEMORY_KIB.to_be_bytes());
+    blob.extend_from_slice(&KDF_PASSES.to_be_bytes());
+    blob.extend_from_slice(&salt);
+    blob.extend_from_slice(&nonce);
+
+    let cipher = backup_cipher(passphrase, &salt, KDF_MEMORY_KIB, KDF_PASSES)?;
+    let seed = Zeroizing::new(signing_key.to_bytes());
+    let sealed = cipher
+        .encrypt(&nonce.into(), Payload { msg: seed.as_ref(), aad: &blob })
+        .map_err(|_| anyhow::anyhow!("Encryption failed"))?;
+    blob.extend_from_slice(&sealed);
+    let checksum = Sha256::digest(&blob);
+    blob.extend_from_slice(&checksum[..4]);
+
+    let body = B64.encode(&blob);
+    let mut out = format!("{BACKUP_BEGIN}\nUser: {username}\n\n");
+    for chunk in body.as_bytes().chunks(64) {
+        out.push_str(&String::from_utf8_lossy(chunk));
+        out.push('\n');
+    }
+    out.push_str(BACKUP_END);
+    out.push('\n');
+    Ok(out)
+}
+
+/// Whether `text` looks like an exported backup rather than a raw seed.
+pub fn is_backup(text: &str) -> bool {
+    text.contains(BACKUP_BEGIN) || backup_body(text).is_some_and(|b| b.starts_with(BACKUP_MAGIC))
+}
+
+/// Extracts the binary payload, tolerating headers, wrapping and pasted line breaks.
+fn backup_body(text: &str) -> Option<Vec<u8>> {
+    let inner = match (text.find(BACKUP_BEGIN), text.find(BACKUP_END)) {
+        (Some(start), Some(end)) if end > start => &text[start + BACKUP_BEGIN.len()..end],
+        _ => text,
+    };
+    // Header lines such as `User: name` carry a colon, which base64 never does.
+    let encoded: String = inner
+        .lines()
+        .filter(|line| !line.contains(':'))
+        .flat_map(str::split_whitespace)
+        .collect();
+    B64.decode(encoded).ok()
+}
+
+pub fn import_backup(text: &str, passphrase: &str) -> Result<SigningKey> {
+    use chacha20poly1305::aead::{Aead, Payload};
+    use sha2::{Digest, Sha256};
+
+    let blob = backup_body(text).context("Not a ttychat key backup (could not read the encoded block)")?;
+    const HEADER: usize = 4 + 1 + 4 + 4 + 16 + 12;
+    if blob.len() < HEADER + 4 || !blob.starts_with(BACKUP_MAGIC) {
+        anyhow::bail!("Not a ttychat key backup");
+    }
+    let (body, checksum) = blob.split_at(blob.len() - 4);
+    if Sha256::digest(body)[..4] != *checksum {
+        anyhow::bail!("Backup checksum does not match; the text is damaged or mistyped");
+    }
+    if body[4] != BACKUP_VERSION {
+        anyhow::bail!("Backup format version {} is not supported", body[4]);
+    }
+
+    let memory = u32::from_be_bytes(body[5..9].try_into()?);
+    let passes = u32::from_be_bytes(body[9..13].try_into()?);
+    if memory > KDF_MAX_MEMORY_KIB || passes > KDF_MAX_PASSES {
+        anyhow::bail!("Backup asks for unreasonable key derivation cost ({memory} KiB, {passes} passes)");
+    }
+    let salt = &body[13..29];
+    let nonce: [u8; 12] = body[29..41].try_into()?;
+    let cipher = backup_cipher(passphrase, salt, memory, passes)?;
+    let seed = Zeroizing::new(
+        cipher
+            .decrypt(&nonce.into(), Payload { msg: &body[HEADER..], aad: &body[..HEADER] })
+            .map_err(|_| anyhow::anyhow!("Wrong passphrase"))?,
+    );
+    let seed: Zeroizing<[u8; 32]> = Zeroizing::new(
+        seed.as_slice()
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Backup holds a key of the wrong size"))?,
+    );
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+/// Renders text as a QR code using half-block characters.
+pub fn qr_text(data: &str) -> Result<String> {
+    use qrcode::render::unicode::Dense1x2;
+    let code = qrcode::QrCode::new(data.as_bytes())?;
+    Ok(code.render::<Dense1x2>().quiet_zone(true).build())
+}
+
 pub fn cmd_gen(username: Option<&str>) -> Result<()> {
     let un = username.unwrap_or("default");
     let key_path = crate::config::Config::key_path(un);
@@ -64,6 +376,15 @@ pub fn cmd_gen(username: Option<&str>) -> Result<()> {
         eprintln!("[*] Loaded existing identity.");
     }
     println!("\nPublic Key (base64):\n  {}", pub_b64);
+    println!("\nFingerprint:\n  {}", fingerprint(&pub_b64).unwrap_or_default());
+    if let Some(symbols) = fingerprint_symbols(&pub_b64) {
+        let emoji: Vec<&str> = symbols.iter().map(|(e, _)| *e).collect();
+        let words: Vec<&str> = symbols.iter().map(|(_, w)| *w).collect();
+        println!("  {}  ({})", emoji.join(" "), words.join(", "));
+    }
+    for line in randomart(&pub_b64).unwrap_or_default() {
+        println!("  {line}");
+    }
     println!("\nTo add yourself, ask an admin to run:");
     println!("  ttychatctl add <pubkey> -user <username>");
     Ok(())
@@ -75,6 +396,7 @@ pub fn cmd_reset(username: Option<&str>) -> Result<()> {
         if key_path.exists() {
             std::fs::remove_file(&key_path)?;
             eprintln!("[*] Deleted identity key for user '{}' at {}", un, key_path.display());
+            eprintln!("    To replace a key without losing access, use: ttychat keys rotate --user {}", un);
         } else {
             eprintln!("[!] No identity key found for user '{}' at {}", un, key_path.display());
         }
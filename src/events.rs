@@ -1,6 +1,9 @@
+use ::notify::{EventKind, RecursiveMode, Watcher};
 use crossterm::event::{Event, EventStream, KeyEvent};
 use futures::StreamExt;
+use std::path::PathBuf;
 use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
 use tokio::sync::mpsc;
 use tokio::time;
 
@@ -11,6 +14,8 @@ pub enum AppEvent {
     Resize,
     FocusGained,
     FocusLost,
+    Paste(String),
+    ConfigReloaded,
 }
 
 pub fn spawn_event_task(tx: mpsc::UnboundedSender<AppEvent>) {
@@ -24,24 +29,55 @@ pub fn spawn_event_task(tx: mpsc::UnboundedSender<AppEvent>) {
                     if tx.send(AppEvent::Tick).is_err() { break; }
                 }
                 maybe_event = reader.next() => {
-                    match maybe_event {
-                        Some(Ok(Event::Key(key))) => {
-                            if tx.send(AppEvent::Key(key)).is_err() { break; }
-                        }
-                        Some(Ok(Event::Resize(_, _))) => {
-                            if tx.send(AppEvent::Resize).is_err() { break; }
-                        }
-                        Some(Ok(Event::FocusGained)) => {
-                            if tx.send(AppEvent::FocusGained).is_err() { break; }
-                        }
-                        Some(Ok(Event::FocusLost)) => {
-                            if tx.send(AppEvent::FocusLost).is_err() { break; }
-                        }
+                    let app_event = match maybe_event {
+                        Some(Ok(Event::Key(key))) => AppEvent::Key(key),
+                        Some(Ok(Event::Resize(_, _))) => AppEvent::Resize,
+                        Some(Ok(Event::FocusGained)) => AppEvent::FocusGained,
+                        Some(Ok(Event::FocusLost)) => AppEvent::FocusLost,
+                        Some(Ok(Event::Paste(text))) => AppEvent::Paste(text),
                         Some(Err(_)) | None => break,
-                        _ => {}
-                    }
+                        _ => continue,
+                    };
+                    if tx.send(app_event).is_err() { break; }
                 }
             }
         }
     });
 }
+
+/// Emits `ConfigReloaded` when the settings file changes or on SIGHUP.
+pub fn spawn_config_watcher(path: PathBuf, tx: mpsc::UnboundedSender<AppEvent>) {
+    let (change_tx, mut change_rx) = mpsc::unbounded_channel();
+    let file_name = path.file_name().map(|n| n.to_os_string());
+    let watcher = path.parent().and_then(|dir| {
+        let _ = std::fs::create_dir_all(dir);
+        let mut watcher = ::notify::recommended_watcher(move |res: ::notify::Result<::notify::Event>| {
+            let Ok(event) = res else { return };
+            if matches!(event.kind, EventKind::Access(_)) {
+                return;
+            }
+            if event.paths.iter().any(|p| p.file_name() == file_name.as_deref()) {
+                let _ = change_tx.send(());
+            }
+        }).ok()?;
+        watcher.watch(dir, RecursiveMode::NonRecursive).ok()?;
+        Some(watcher)
+    });
+    let mut hangup = signal(SignalKind::hangup()).ok();
+
+    tokio::spawn(async move {
+        let _watcher = watcher;
+        loop {
+            tokio::select! {
+                Some(()) = change_rx.recv() => {
+                    // Editors save in several steps; wait for the file to settle.
+                    time::sleep(Duration::from_millis(250)).await;
+                    while change_rx.try_recv().is_ok() {}
+                }
+                Some(()) = async { hangup.as_mut()?.recv().await } => {}
+                else => break,
+            }
+            if tx.send(AppEvent::ConfigReloaded).is_err() { break; }
+        }
+    });
+}
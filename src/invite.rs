@@ -0,0 +1,57 @@
+/// An invite link of the form `ttychat://host[:port]/enroll?code=...&user=...`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invite {
+    pub server: String,
+    pub code: String,
+    pub username: Option<String>,
+}
+
+impl Invite {
+    pub fn parse(s: &str) -> Option<Self> {
+        let rest = s.trim().strip_prefix("ttychat://")?;
+        let (server, rest) = rest.split_once('/')?;
+        let (path, query) = rest.split_once('?')?;
+        if server.is_empty() || path.trim_end_matches('/') != "enroll" {
+            return None;
+        }
+
+        let mut code = None;
+        let mut username = None;
+        for pair in query.split('&') {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            match key {
+                "code" => code = Some(percent_decode(value)?),
+                "user" | "username" => username = Some(percent_decode(value)?),
+                _ => {}
+            }
+        }
+        Some(Self {
+            server: server.to_string(),
+            code: code.filter(|c| !c.is_empty())?,
+            username: username.filter(|u| !u.is_empty()),
+        })
+    }
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                out.push(u8::from_str_radix(s.get(i + 1..i + 3)?, 16).ok()?);
+                i += 3;
+            }
+            b'+' => {
+                out.push(b' ');
+                i += 1;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).ok()
+}
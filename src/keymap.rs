@@ -0,0 +1,413 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Send,
+    EditLast,
+    ScrollUp,
+    ScrollDown,
+    PageUp,
+    PageDown,
+    ScrollTop,
+    ScrollBottom,
+    JumpUnread,
+    ToggleUsers,
+    ClearInput,
+    MessageDetails,
+    SelectMode,
+    Help,
+    NextField,
+    PrevField,
+    Quit,
+}
+
+impl Action {
+    /// All actions, in the order they are listed in the help overlay.
+    pub const ALL: [Action; 17] = [
+        Self::Send,
+        Self::EditLast,
+        Self::ScrollUp,
+        Self::ScrollDown,
+        Self::PageUp,
+        Self::PageDown,
+        Self::ScrollTop,
+        Self::ScrollBottom,
+        Self::JumpUnread,
+        Self::ToggleUsers,
+        Self::ClearInput,
+        Self::MessageDetails,
+        Self::SelectMode,
+        Self::Help,
+        Self::NextField,
+        Self::PrevField,
+        Self::Quit,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Send => "send",
+            Self::EditLast => "edit_last",
+            Self::ScrollUp => "scroll_up",
+            Self::ScrollDown => "scroll_down",
+            Self::PageUp => "page_up",
+            Self::PageDown => "page_down",
+            Self::ScrollTop => "scroll_top",
+            Self::ScrollBottom => "scroll_bottom",
+            Self::JumpUnread => "jump_unread",
+            Self::ToggleUsers => "toggle_users",
+            Self::ClearInput => "clear_input",
+            Self::MessageDetails => "message_details",
+            Self::SelectMode => "select_mode",
+            Self::Help => "help",
+            Self::NextField => "next_field",
+            Self::PrevField => "prev_field",
+            Self::Quit => "quit",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Send => "Send message",
+            Self::EditLast => "Edit your last message (empty input)",
+            Self::ScrollUp => "Scroll up 1 line",
+            Self::ScrollDown => "Scroll down 1 line",
+            Self::PageUp => "Scroll up 10 lines",
+            Self::PageDown => "Scroll down 10 lines",
+            Self::ScrollTop => "Jump to oldest",
+            Self::ScrollBottom => "Jump to latest",
+            Self::JumpUnread => "Jump to first unread",
+            Self::ToggleUsers => "Focus input/userlist",
+            Self::ClearInput => "Clear input",
+            Self::MessageDetails => "Details of latest message",
+            Self::SelectMode => "Select messages (y/Y/r/i)",
+            Self::Help => "Toggle this help",
+            Self::NextField => "Next form field",
+            Self::PrevField => "Previous form field",
+            Self::Quit => "Quit",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.name() == s)
+    }
+
+    pub fn scope(&self) -> Scope {
+        match self {
+            Self::Help | Self::Quit => Scope::Global,
+            Self::NextField | Self::PrevField => Scope::Form,
+            _ => Scope::Chat,
+        }
+    }
+
+    fn active_in(&self, ctx: &KeyContext) -> bool {
+        let in_scope = self.scope() == Scope::Global || self.scope() == ctx.scope;
+        in_scope && (*self != Self::EditLast || ctx.can_edit_last)
+    }
+}
+
+/// Where an action means something.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scope {
+    /// Everywhere; as a context, a screen with no actions of its own.
+    #[default]
+    Global,
+    Form,
+    Chat,
+}
+
+/// What the focused screen can do with a key right now.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeyContext {
+    pub scope: Scope,
+    /// A text field has focus, so unmodified printable keys are text and
+    /// never start a binding.
+    pub typing: bool,
+    /// There is an own message that `EditLast` would open.
+    pub can_edit_last: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyPreset {
+    #[default]
+    Default,
+    Vim,
+    Emacs,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyConfig {
+    pub preset: KeyPreset,
+    pub confirm_quit: bool,
+    /// Key sequence → action name, e.g. `"ctrl+x ctrl+c" = "quit"` or `"ctrl+u" = "none"`.
+    pub bindings: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPress {
+    pub code: KeyCode,
+    pub mods: KeyModifiers,
+}
+
+impl KeyPress {
+    pub fn from_event(key: &KeyEvent) -> Self {
+        let mut mods = key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let code = match key.code {
+            KeyCode::Char(c) if mods.contains(KeyModifiers::SHIFT) || c.is_uppercase() => {
+                mods.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            KeyCode::BackTab => {
+                mods.remove(KeyModifiers::SHIFT);
+                KeyCode::BackTab
+            }
+            code => code,
+        };
+        Self { code, mods }
+    }
+
+    /// An unmodified key that types a character.
+    fn is_text(&self) -> bool {
+        matches!(self.code, KeyCode::Char(_)) && !self.mods.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        let mut mods = KeyModifiers::NONE;
+        let mut rest = s;
+        loop {
+            let lower = rest.to_ascii_lowercase();
+            let (m, len) = if lower.starts_with("ctrl+") {
+                (KeyModifiers::CONTROL, 5)
+            } else if lower.starts_with("alt+") {
+                (KeyModifiers::ALT, 4)
+            } else if lower.starts_with("shift+") {
+                (KeyModifiers::SHIFT, 6)
+            } else {
+                break;
+            };
+            mods |= m;
+            rest = &rest[len..];
+        }
+
+        let code = match rest.to_ascii_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            f if f.len() > 1 && f.starts_with('f') => KeyCode::F(f[1..].parse().ok()?),
+            _ => {
+                let mut chars = rest.chars();
+                let c = chars.next()?;
+                if chars.next().is_some() {
+                    return None;
+                }
+                KeyCode::Char(c)
+            }
+        };
+        Some(Self::from_event(&KeyEvent::new(code, mods)))
+    }
+
+    pub fn label(&self) -> String {
+        let mut s = String::new();
+        if self.mods.contains(KeyModifiers::CONTROL) {
+            s.push_str("Ctrl-");
+        }
+        if self.mods.contains(KeyModifiers::ALT) {
+            s.push_str("Alt-");
+        }
+        match self.code {
+            KeyCode::Char(' ') => s.push_str("Space"),
+            KeyCode::Char(c) if !self.mods.is_empty() => s.push(c.to_ascii_uppercase()),
+            KeyCode::Char(c) => s.push(c),
+            KeyCode::Up => s.push('↑'),
+            KeyCode::Down => s.push('↓'),
+            KeyCode::Left => s.push('←'),
+            KeyCode::Right => s.push('→'),
+            KeyCode::PageUp => s.push_str("PgUp"),
+            KeyCode::PageDown => s.push_str("PgDn"),
+            KeyCode::BackTab => s.push_str("Shift-Tab"),
+            KeyCode::F(n) => s.push_str(&format!("F{n}")),
+            other => s.push_str(&format!("{other:?}")),
+        }
+        s
+    }
+}
+
+fn parse_sequence(s: &str) -> Option<Vec<KeyPress>> {
+    let seq: Option<Vec<KeyPress>> = s.split_whitespace().map(KeyPress::parse).collect();
+    seq.filter(|v| !v.is_empty())
+}
+
+pub enum KeyResult {
+    /// Actions bound to the completed sequence; the screen picks the one it understands.
+    Actions(Vec<Action>),
+    /// The key started or continued a chord.
+    Pending,
+    Unbound,
+    /// A chord broke off. The keys it held back go to the screen as if
+    /// unbound; the key that broke it has to be fed again.
+    Broken(Vec<KeyEvent>),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    bindings: Vec<(Vec<KeyPress>, Action)>,
+    pending: Vec<KeyEvent>,
+}
+
+const DEFAULT_BINDINGS: &[(&str, Action)] = &[
+    ("enter", Action::Send),
+    ("up", Action::EditLast),
+    ("up", Action::ScrollUp),
+    ("down", Action::ScrollDown),
+    ("pageup", Action::PageUp),
+    ("pagedown", Action::PageDown),
+    ("home", Action::ScrollTop),
+    ("end", Action::ScrollBottom),
+    ("ctrl+n", Action::JumpUnread),
+    ("tab", Action::ToggleUsers),
+    ("ctrl+u", Action::ClearInput),
+    ("ctrl+t", Action::MessageDetails),
+    ("esc", Action::SelectMode),
+    ("ctrl+k", Action::SelectMode),
+    ("f1", Action::Help),
+    ("tab", Action::NextField),
+    ("backtab", Action::PrevField),
+    ("ctrl+c", Action::Quit),
+];
+
+const VIM_BINDINGS: &[(&str, Action)] = &[
+    ("ctrl+y", Action::ScrollUp),
+    ("ctrl+e", Action::ScrollDown),
+    ("ctrl+b", Action::PageUp),
+    ("ctrl+f", Action::PageDown),
+    ("ctrl+w g", Action::ScrollTop),
+    ("ctrl+w G", Action::ScrollBottom),
+    ("ctrl+w w", Action::ToggleUsers),
+    ("ctrl+w q", Action::Quit),
+];
+
+const EMACS_BINDINGS: &[(&str, Action)] = &[
+    ("ctrl+p", Action::ScrollUp),
+    ("ctrl+n", Action::ScrollDown),
+    ("alt+v", Action::PageUp),
+    ("ctrl+v", Action::PageDown),
+    ("alt+<", Action::ScrollTop),
+    ("alt+>", Action::ScrollBottom),
+    ("ctrl+x n", Action::JumpUnread),
+    ("ctrl+x o", Action::ToggleUsers),
+    ("ctrl+x ctrl+c", Action::Quit),
+];
+
+impl Keymap {
+    /// Builds the keymap from the preset plus user bindings. Invalid entries are
+    /// skipped and returned as messages.
+    pub fn from_config(cfg: &KeyConfig) -> (Self, Vec<String>) {
+        let mut map = Self::default();
+        let preset = match cfg.preset {
+            KeyPreset::Default => &[][..],
+            KeyPreset::Vim => VIM_BINDINGS,
+            KeyPreset::Emacs => EMACS_BINDINGS,
+        };
+        for &(keys, action) in DEFAULT_BINDINGS {
+            map.bind(parse_sequence(keys).unwrap_or_default(), Some(action), false);
+        }
+        for &(keys, action) in preset {
+            map.bind(parse_sequence(keys).unwrap_or_default(), Some(action), true);
+        }
+
+        let mut errors = Vec::new();
+        for (keys, action) in &cfg.bindings {
+            let Some(seq) = parse_sequence(keys) else {
+                errors.push(format!("Unknown key sequence in [keys.bindings]: {keys}"));
+                continue;
+            };
+            match Action::parse(action) {
+                Some(a) => map.bind(seq, Some(a), true),
+                None if action == "none" => map.bind(seq, None, true),
+                None => errors.push(format!("Unknown action in [keys.bindings]: {action}")),
+            }
+        }
+        (map, errors)
+    }
+
+    fn bind(&mut self, seq: Vec<KeyPress>, action: Option<Action>, replace: bool) {
+        if replace {
+            self.bindings.retain(|(s, _)| *s != seq);
+            if let Some(a) = action {
+                // A rebound action loses its old keys only when they would now shadow it.
+                self.bindings.retain(|(s, b)| *b != a || !seq.starts_with(s));
+            }
+        }
+        if let Some(a) = action {
+            self.bindings.push((seq, a));
+        }
+    }
+
+    pub fn feed(&mut self, key: &KeyEvent, ctx: &KeyContext) -> KeyResult {
+        self.pending.push(*key);
+        let pending: Vec<KeyPress> = self.pending.iter().map(KeyPress::from_event).collect();
+        let live = self.bindings.iter().filter(|(s, a)| {
+            a.active_in(ctx) && !(ctx.typing && s.first().is_some_and(KeyPress::is_text))
+        });
+        let mut exact = Vec::new();
+        let mut longer = false;
+        for (s, a) in live {
+            if *s == pending {
+                exact.push(*a);
+            } else if s.len() > pending.len() && s.starts_with(&pending) {
+                longer = true;
+            }
+        }
+        // EditLast is only live when it has a message to open, and then it
+        // replaces whatever else shares its key.
+        if exact.contains(&Action::EditLast) {
+            exact.retain(|a| *a == Action::EditLast);
+        }
+
+        if !exact.is_empty() {
+            self.pending.clear();
+            return KeyResult::Actions(exact);
+        }
+        if longer {
+            return KeyResult::Pending;
+        }
+        let mut held = std::mem::take(&mut self.pending);
+        held.pop();
+        if held.is_empty() {
+            KeyResult::Unbound
+        } else {
+            KeyResult::Broken(held)
+        }
+    }
+
+    pub fn pending_label(&self) -> Option<String> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let keys: Vec<String> = self.pending.iter().map(|k| KeyPress::from_event(k).label()).collect();
+        Some(keys.join(" "))
+    }
+
+    /// Key labels bound to `action`, e.g. `["Esc", "Ctrl-K"]`.
+    pub fn keys_for(&self, action: Action) -> Vec<String> {
+        self.bindings.iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(seq, _)| seq.iter().map(KeyPress::label).collect::<Vec<_>>().join(" "))
+            .collect()
+    }
+}
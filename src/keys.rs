@@ -0,0 +1,345 @@
+use anyhow::{bail, Context, Result};
+use ed25519_dalek::SigningKey;
+use std::io::{BufRead, IsTerminal, Read, Write};
+use std::path::PathBuf;
+use zeroize::Zeroizing;
+
+use crate::config::{Config, Overrides};
+use crate::crypto::{self, LockedKey};
+use crate::net::{BareMsg, DeviceKey, KeyAddMsg, KeyRevokeMsg, KeyRotateMsg, Link, Refused};
+
+pub const USAGE: &str = "ttychat keys list|rotate [--discard-pending]|add <pubkey> [--label NAME]|revoke <fingerprint>\
+|export [--out FILE] [--qr]|import [FILE] [--force] [--yes] \
+[--server HOST:PORT] [--user NAME] [--key FILE] [--insecure] [--config FILE] [--port N]";
+
+struct KeyArgs {
+    server: Option<String>,
+    username: String,
+    insecure: bool,
+    key_path: PathBuf,
+    label: String,
+    out: Option<PathBuf>,
+    qr: bool,
+    force: bool,
+    yes: bool,
+    discard_pending: bool,
+    rest: Vec<String>,
+}
+
+impl KeyArgs {
+    /// Parses flags, defaulting to the last server and username used in the app.
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut server = None;
+        let mut username = None;
+        let mut key_path = None;
+        let mut insecure = None;
+        let mut label = String::new();
+        let mut out = None;
+        let mut qr = false;
+        let mut force = false;
+        let mut yes = false;
+        let mut discard_pending = false;
+        let mut rest = Vec::new();
+        let mut global = Vec::new();
+
+        let mut it = args.iter();
+        while let Some(arg) = it.next() {
+            let mut value = || it.next().cloned().with_context(|| format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--server" => server = Some(value()?),
+                "--user" => username = Some(value()?),
+                "--key" => key_path = Some(PathBuf::from(value()?)),
+                "--label" => label = value()?,
+                "--insecure" => insecure = Some(true),
+                "--out" => out = Some(PathBuf::from(value()?)),
+                "--qr" => qr = true,
+                "--force" => force = true,
+                "--yes" => yes = true,
+                "--discard-pending" => discard_pending = true,
+                "--config" | "--port" | "--theme" | "--history" => global.extend([arg.clone(), value()?]),
+                flag if flag.starts_with("--") => bail!("unknown option: {flag}"),
+                _ => rest.push(arg.clone()),
+            }
+        }
+
+        let overrides = Overrides::from_env_and_args(&global).map_err(anyhow::Error::msg)?;
+        let (config, _) = Config::load(overrides);
+        let server = server.or(config.last_server.clone())
+            .map(|s| if s.contains(':') { s } else { format!("{s}:{}", config.default_port()) });
+        let username = username.or(config.last_username.clone())
+            .context("No username known yet; pass --user NAME")?;
+        let profile = server.as_deref().and_then(|server| {
+            config.profiles.iter().find(|p| p.matches(server, &username, config.default_port()))
+        });
+
+        Ok(Self {
+            insecure: insecure.or(profile.map(|p| p.insecure)).unwrap_or(false),
+            key_path: key_path
+                .or(profile.and_then(|p| p.key_file.clone()))
+                .unwrap_or_else(|| Config::key_path(&username)),
+            server,
+            username,
+            label,
+            out,
+            qr,
+            force,
+            yes,
+            discard_pending,
+            rest,
+        })
+    }
+
+    fn server(&self) -> Result<&str> {
+        self.server.as_deref().context("No server known yet; pass --server HOST:PORT")
+    }
+
+    /// Where a rotation keeps the new key until the server has accepted it.
+    fn pending_path(&self) -> PathBuf {
+        self.key_path.with_extension("key.new")
+    }
+
+    fn load_key(&self) -> Result<LockedKey> {
+        if !self.key_path.exists() {
+            bail!("No identity key at {}", self.key_path.display());
+        }
+        Ok(LockedKey::new(crypto::load_or_generate(&self.key_path)?.0))
+    }
+}
+
+pub async fn cmd_keys(args: &[String]) -> Result<()> {
+    let Some(sub) = args.first() else {
+        bail!("usage: {USAGE}");
+    };
+    let opts = KeyArgs::parse(&args[1..])?;
+    // Backups work offline, so handle them before logging in.
+    match sub.as_str() {
+        "export" => return export(&opts, &*opts.load_key()?),
+        "import" => return import(&opts),
+        "rotate" if opts.pending_path().exists() => return resume_rotation(&opts).await,
+        _ => {}
+    }
+    let key = opts.load_key()?;
+    let mut link = Link::login(opts.server()?, opts.insecure, &opts.username, &key).await?;
+
+    match sub.as_str() {
+        "list" => {
+            let keys = list_keys(&mut link).await?;
+            print_keys(&opts, &key, &keys);
+        }
+        "rotate" => rotate(&opts, &key, &mut link).await?,
+        "add" => {
+            let pubkey = opts.rest.first().context("usage: ttychat keys add <pubkey> [--label NAME]")?;
+            crypto::fingerprint(pubkey).context("Not a valid base64 public key")?;
+            let msg = KeyAddMsg {
+                msg_type: "key_add".into(),
+                pubkey: pubkey.clone(),
+                label: opts.label.clone(),
+                sig: crypto::endorse(&key, &opts.username, pubkey)?,
+            };
+            link.request(&msg, &["key_add_ok", "key_list"]).await?;
+            eprintln!("[*] Added device key {}", crypto::fingerprint(pubkey).unwrap_or_default());
+        }
+        "revoke" => {
+            let wanted = opts.rest.first().context("usage: ttychat keys revoke <fingerprint>")?;
+            let keys = list_keys(&mut link).await?;
+            let target = find_key(&keys, wanted)?;
+            if target.pubkey == crypto::pubkey_b64(&key) {
+                bail!("That is this device's key; use `ttychat keys rotate` to replace it");
+            }
+            let msg = KeyRevokeMsg { msg_type: "key_revoke".into(), pubkey: target.pubkey.clone() };
+            link.request(&msg, &["key_revoke_ok", "key_list"]).await?;
+            eprintln!("[*] Revoked device key {}", crypto::fingerprint(&target.pubkey).unwrap_or_default());
+        }
+        other => bail!("unknown keys command: {other}\nusage: {USAGE}"),
+    }
+    Ok(())
+}
+
+async fn list_keys(link: &mut Link) -> Result<Vec<DeviceKey>> {
+    let reply = link.request(&BareMsg { msg_type: "key_list".into() }, &["key_list"]).await?;
+    let data = reply.get("data").cloned().unwrap_or_default();
+    serde_json::from_value(data).context("Malformed key list from server")
+}
+
+fn print_keys(opts: &KeyArgs, current: &SigningKey, keys: &[DeviceKey]) {
+    let own = crypto::pubkey_b64(current);
+    println!("Device keys for {} on {}:", opts.username, opts.server.as_deref().unwrap_or_default());
+    for k in keys {
+        let marker = if k.pubkey == own { "*" } else { " " };
+        let fp = crypto::fingerprint(&k.pubkey).unwrap_or_else(|| "?".into());
+        let label = if k.label.is_empty() { "(unnamed)" } else { &k.label };
+        let added = k.added.as_deref().map(|t| format!("  added {t}")).unwrap_or_default();
+        let seen = k.last_seen.as_deref().map(|t| format!("  last seen {t}")).unwrap_or_default();
+        println!("  {marker} {fp}  {label}{added}{seen}");
+    }
+    println!("\n  * = this device");
+}
+
+/// Accepts a full or leading part of a fingerprint, or the public key itself.
+fn find_key<'a>(keys: &'a [DeviceKey], wanted: &str) -> Result<&'a DeviceKey> {
+    let wanted = wanted.trim();
+    let prefix = wanted.to_lowercase();
+    let matches: Vec<&DeviceKey> = keys.iter()
+        .filter(|k| {
+            k.pubkey == wanted
+                || crypto::fingerprint(&k.pubkey).is_some_and(|fp| fp.starts_with(&prefix))
+        })
+        .collect();
+    match matches.as_slice() {
+        [one] => Ok(one),
+        [] => bail!("No device key matches {wanted}"),
+        _ => bail!("{wanted} matches several keys; give more of the fingerprint"),
+    }
+}
+
+/// Replaces this device's key. The new key is signed by the old one and only
+/// takes the old one's place on disk once the server has accepted it.
+async fn rotate(opts: &KeyArgs, old: &SigningKey, link: &mut Link) -> Result<()> {
+    let new = crypto::generate();
+    let new_pub = crypto::pubkey_b64(&new);
+    let pending = opts.pending_path();
+    crypto::save_key(&pending, &new)?;
+
+    let msg = KeyRotateMsg {
+        msg_type: "key_rotate".into(),
+        old_pubkey: crypto::pubkey_b64(old),
+        new_pubkey: new_pub.clone(),
+        sig: crypto::endorse(old, &opts.username, &new_pub)?,
+    };
+    // Only a refusal proves the server never took the new key; after a dropped
+    // connection it may have, and then the pending file is the only copy.
+    if let Err(e) = link.request(&msg, &["key_rotate_ok"]).await {
+        if e.is::<Refused>() {
+            let _ = std::fs::remove_file(&pending);
+            return Err(e);
+        }
+        return Err(e.context(format!(
+            "No answer to the rotation; the new key is kept at {} in case the server accepted it",
+            pending.display()
+        )));
+    }
+    std::fs::rename(&pending, &opts.key_path).with_context(|| {
+        format!("Server accepted the new key but it could not be moved into place; it is at {}", pending.display())
+    })?;
+
+    eprintln!("[*] Rotated identity key for '{}'", opts.username);
+    eprintln!("    Old: {}", crypto::fingerprint(&crypto::pubkey_b64(old)).unwrap_or_default());
+    eprintln!("    New: {}", crypto::fingerprint(&new_pub).unwrap_or_default());
+    Ok(())
+}
+
+/// Deals with a `.key.new` left by an interrupted rotation before starting
+/// another, since it may be the only copy of the key the server now trusts.
+async fn resume_rotation(opts: &KeyArgs) -> Result<()> {
+    let pending = opts.pending_path();
+    if opts.discard_pending {
+        std::fs::remove_file(&pending).with_context(|| format!("Could not remove {}", pending.display()))?;
+        eprintln!("[*] Discarded {}; run `ttychat keys rotate` again", pending.display());
+        return Ok(());
+    }
+    let candidate = LockedKey::new(crypto::load_or_generate(&pending)?.0);
+    if let Err(e) = Link::login(opts.server()?, opts.insecure, &opts.username, &candidate).await {
+        bail!(
+            "{} is left from an interrupted rotation and does not log in ({e:#}).
+             If the server never took it, delete it with `ttychat keys rotate --discard-pending`.",
+            pending.display()
+        );
+    }
+    std::fs::rename(&pending, &opts.key_path)
+        .with_context(|| format!("Could not move {} into place", pending.display()))?;
+    eprintln!("[*] The server had accepted the interrupted rotation; {} now holds the new key", opts.key_path.display());
+    eprintln!("    New: {}", crypto::fingerprint(&crypto::pubkey_b64(&candidate)).unwrap_or_default());
+    Ok(())
+}
+
+/// Writes a passphrase-protected backup of the identity key to a file or stdout.
+fn export(opts: &KeyArgs, key: &SigningKey) -> Result<()> {
+    let passphrase = Zeroizing::new(rpassword::prompt_password("Backup passphrase: ")?);
+    if passphrase.chars().count() < 8 {
+        bail!("Passphrase must be at least 8 characters");
+    }
+    if *Zeroizing::new(rpassword::prompt_password("Repeat passphrase: ")?) != *passphrase {
+        bail!("Passphrases do not match");
+    }
+    let backup = crypto::export_backup(key, &passphrase, &opts.username)?;
+
+    match &opts.out {
+        Some(path) => {
+            write_private(path, backup.as_bytes())?;
+            eprintln!("[*] Backup written to {}", path.display());
+        }
+        None => print!("{backup}"),
+    }
+    if opts.qr {
+        println!("{}", crypto::qr_text(backup.trim_end())?);
+    }
+    eprintln!("[*] Key fingerprint: {}", crypto::fingerprint(&crypto::pubkey_b64(key)).unwrap_or_default());
+    eprintln!("    Keep the passphrase separately; the backup is useless without it.");
+    Ok(())
+}
+
+/// Restores a key from a backup, showing what it decrypts to before saving.
+fn import(opts: &KeyArgs) -> Result<()> {
+    let text = match opts.rest.first() {
+        Some(path) => std::fs::read_to_string(path).with_context(|| format!("Could not read {path}"))?,
+        None => {
+            if std::io::stdin().is_terminal() {
+                eprintln!("[*] Paste the backup, then press Ctrl-D:");
+            }
+            let mut buf = String::new();
+            std::io::stdin().read_to_string(&mut buf)?;
+            buf
+        }
+    };
+    if !crypto::is_backup(&text) {
+        bail!("That is not a ttychat key backup");
+    }
+    let passphrase = Zeroizing::new(rpassword::prompt_password("Backup passphrase: ")?);
+    let key = crypto::import_backup(&text, &passphrase)?;
+    let pubkey = crypto::pubkey_b64(&key);
+
+    eprintln!("[*] Backup decrypted");
+    eprintln!("    Public key:  {pubkey}");
+    eprintln!("    Fingerprint: {}", crypto::fingerprint(&pubkey).unwrap_or_default());
+    if let Some(symbols) = crypto::fingerprint_symbols(&pubkey) {
+        let emoji: Vec<&str> = symbols.iter().map(|(e, _)| *e).collect();
+        eprintln!("    Emoji:       {}", emoji.join(" "));
+    }
+
+    if opts.key_path.exists() {
+        let existing = crypto::load_or_generate(&opts.key_path)?.0;
+        if crypto::pubkey_b64(&existing) == pubkey {
+            eprintln!("[*] {} already holds this key", opts.key_path.display());
+            return Ok(());
+        }
+        if !opts.force {
+            bail!("{} holds a different key; pass --force to replace it", opts.key_path.display());
+        }
+        eprintln!("[!] This replaces the different key at {}", opts.key_path.display());
+    }
+
+    // Stdin may be the backup itself, so the answer comes from the terminal.
+    if !opts.yes {
+        let tty = std::fs::File::open("/dev/tty").context("No terminal to confirm on; pass --yes to save without asking")?;
+        eprint!("Save to {}? [y/N] ", opts.key_path.display());
+        std::io::stderr().flush()?;
+        let mut answer = String::new();
+        std::io::BufReader::new(tty).read_line(&mut answer)?;
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            bail!("Import cancelled");
+        }
+    }
+    crypto::save_key(&opts.key_path, &key)?;
+    eprintln!("[*] Key saved to {}", opts.key_path.display());
+    Ok(())
+}
+
+fn write_private(path: &std::path::Path, data: &[u8]) -> Result<()> {
+    std::fs::write(path, data).with_context(|| format!("Could not write {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
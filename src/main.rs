@@ -7,9 +7,13 @@ use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
 
 mod app;
+mod clipboard;
 mod config;
 mod crypto;
 mod events;
+mod invite;
+mod keys;
+mod keymap;
 mod net;
 mod notify;
 mod ui;
@@ -23,6 +27,10 @@ async fn main() -> Result<()> {
         .install_default()
         .expect("Failed to install rustls ring crypto provider");
 
+    if let Err(e) = crypto::disable_core_dumps() {
+        eprintln!("[!] Could not disable core dumps: {e}");
+    }
+
     let args: Vec<String> = std::env::args().collect();
 
     if args.get(1).map(|s| s.as_str()) == Some("gen") {
@@ -35,6 +43,22 @@ async fn main() -> Result<()> {
         return crypto::cmd_reset(name);
     }
 
+    if args.get(1).map(|s| s.as_str()) == Some("keys") {
+        return keys::cmd_keys(&args[2..]).await;
+    }
+
+    let overrides = match config::Overrides::from_env_and_args(&args[1..]) {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("ttychat: {e}");
+            eprintln!("usage: ttychat [--config FILE] [--port N] [--theme default|mono] [--history N] [INVITE]");
+            eprintln!("       INVITE is a ttychat://host:port/enroll?code=...&user=... link");
+            eprintln!("       ttychat gen [username] | reset [username]");
+            eprintln!("       {}", keys::USAGE);
+            std::process::exit(2);
+        }
+    };
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(
@@ -42,6 +66,7 @@ async fn main() -> Result<()> {
         EnterAlternateScreen,
         crossterm::cursor::Hide,
         crossterm::event::EnableFocusChange,
+        crossterm::event::EnableBracketedPaste,
     )?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
@@ -51,7 +76,7 @@ async fn main() -> Result<()> {
     unsafe {
         if saved_stderr >= 0 {
             let devnull = libc::open(
-                b"/dev/null\0".as_ptr() as *const libc::c_char,
+                c"/dev/null".as_ptr(),
                 libc::O_WRONLY | libc::O_CLOEXEC,
             );
             if devnull >= 0 {
@@ -64,11 +89,16 @@ async fn main() -> Result<()> {
     let original_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic_info| {
         let _ = disable_raw_mode();
-        let _ = execute!(io::stdout(), LeaveAlternateScreen, crossterm::cursor::Show);
+        let _ = execute!(
+            io::stdout(),
+            LeaveAlternateScreen,
+            crossterm::cursor::Show,
+            crossterm::event::DisableBracketedPaste,
+        );
         original_hook(panic_info);
     }));
 
-    let result = App::new().run(&mut terminal).await;
+    let result = App::new(overrides).run(&mut terminal).await;
 
     disable_raw_mode()?;
     unsafe {
@@ -82,6 +112,7 @@ async fn main() -> Result<()> {
         LeaveAlternateScreen,
         crossterm::cursor::Show,
         crossterm::event::DisableFocusChange,
+        crossterm::event::DisableBracketedPaste,
         crossterm::terminal::SetTitle(""),
     )?;
     terminal.show_cursor()?;
@@ -1,11 +1,12 @@
 use anyhow::{bail, Context, Result};
 use rustls::pki_types::ServerName;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use ed25519_dalek::SigningKey;
+use tokio::io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, ReadHalf, WriteHalf};
 use tokio::net::TcpStream;
 use tokio::sync::mpsc;
-use tokio_rustls::TlsConnector;
+use tokio_rustls::{client::TlsStream, TlsConnector};
 
 pub mod net_types {
     use anyhow::Result;
@@ -42,6 +43,51 @@ pub struct AuthResponse {
     #[serde(rename = "type")]
     pub msg_type: String,
     pub reason: Option<String>,
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub is_admin: bool,
+    #[serde(default)]
+    pub code: Option<String>,
+}
+
+/// One of the public keys registered for an account, one per device.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DeviceKey {
+    pub pubkey: String,
+    #[serde(default)]
+    pub label: String,
+    #[serde(default)]
+    pub added: Option<String>,
+    #[serde(default)]
+    pub last_seen: Option<String>,
+}
+
+/// Why the server refused a login or enrollment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthFailure {
+    /// Plain key login failed; the key has to be enrolled with an invite.
+    NotEnrolled(String),
+    InvalidCode,
+    CodeExpired,
+    UsernameTaken,
+    Rejected(String),
+}
+
+impl AuthFailure {
+    /// Goes by the server's machine-readable `code` only; the reason text is
+    /// for display and may be worded or translated differently.
+    pub fn classify(enrolling: bool, code: Option<&str>, reason: &str) -> Self {
+        if !enrolling {
+            return Self::NotEnrolled(reason.to_string());
+        }
+        match code {
+            Some("invalid_code") => Self::InvalidCode,
+            Some("code_expired") => Self::CodeExpired,
+            Some("username_taken") => Self::UsernameTaken,
+            _ => Self::Rejected(reason.to_string()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -49,13 +95,141 @@ pub struct ClientMsg {
     #[serde(rename = "type")]
     pub msg_type: String,
     pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DirectMsg {
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    pub to: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EditMsg {
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    pub id: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeleteMsg {
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReactionMsg {
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    pub id: String,
+    pub emoji: String,
+    pub remove: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusMsg {
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    pub state: String,
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AdminCmdMsg {
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    pub action: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub args: Option<serde_json::Map<String, serde_json::Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub req_id: Option<u64>,
+}
+
+/// A message that is nothing but its type, e.g. `typing` or `key_list`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BareMsg {
+    #[serde(rename = "type")]
+    pub msg_type: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyRevokeMsg {
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    pub pubkey: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyAddMsg {
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    pub pubkey: String,
+    pub label: String,
+    pub sig: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyRotateMsg {
+    #[serde(rename = "type")]
+    pub msg_type: String,
+    pub old_pubkey: String,
+    pub new_pubkey: String,
+    pub sig: String,
+}
+
+/// The server answered a [`Link::request`] with an `error`, as opposed to the
+/// connection failing before an answer arrived.
+#[derive(Debug)]
+pub struct Refused(pub String);
+
+impl std::fmt::Display for Refused {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Server refused: {}", self.0)
+    }
+}
+
+impl std::error::Error for Refused {}
+
+/// Writes one message as a JSON line.
+async fn write_line<W: AsyncWrite + Unpin, T: Serialize>(writer: &mut W, msg: &T) -> Result<()> {
+    let mut out = serde_json::to_vec(msg)?;
+    out.push(b'\n');
+    writer.write_all(&out).await?;
+    Ok(())
+}
+
+fn de_opt_id<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    Ok(match Option::<serde_json::Value>::deserialize(d)? {
+        Some(serde_json::Value::String(s)) => Some(s),
+        Some(serde_json::Value::Number(n)) => Some(n.to_string()),
+        _ => None,
+    })
+}
+
+/// Like `de_opt_id`, but anything other than a string is dropped, since
+/// `status` means different things in different server messages.
+fn de_opt_string<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    Ok(match Option::<serde_json::Value>::deserialize(d)? {
+        Some(serde_json::Value::String(s)) => Some(s),
+        _ => None,
+    })
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
 pub struct ServerMsg {
     #[serde(rename = "type")]
     pub msg_type: String,
+    #[serde(default, deserialize_with = "de_opt_id")]
+    pub id: Option<String>,
+    #[serde(default, deserialize_with = "de_opt_id")]
+    pub reply_to: Option<String>,
     pub from: Option<String>,
+    pub to: Option<String>,
     pub text: Option<String>,
     #[serde(alias = "time", alias = "ts", alias = "created", alias = "created_at", alias = "datetime", alias = "date")]
     pub timestamp: Option<serde_json::Value>,
@@ -65,7 +239,16 @@ pub struct ServerMsg {
     pub list: Option<Vec<String>>,
     pub user_list: Option<Vec<String>>,
     pub action: Option<String>,
-    pub data: Option<String>,
+    pub data: Option<serde_json::Value>,
+    #[serde(default, deserialize_with = "de_opt_id")]
+    pub req_id: Option<String>,
+    pub emoji: Option<String>,
+    #[serde(default)]
+    pub remove: bool,
+    #[serde(default, deserialize_with = "de_opt_string")]
+    pub state: Option<String>,
+    #[serde(default, deserialize_with = "de_opt_string")]
+    pub status: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -73,23 +256,88 @@ pub enum NetEvent {
     Connected,
     AuthOk {
         username: String,
+        is_admin: bool,
     },
     AuthFail {
-        reason: String,
+        reason: AuthFailure,
     },
     Message(Box<ServerMsg>),
+    DeviceKeys(Vec<DeviceKey>),
     AdminResponse {
         action: String,
-        data: String,
+        data: serde_json::Value,
+        req_id: Option<String>,
     },
+    Typing {
+        user: String,
+        active: bool,
+    },
+    /// Something worth a line in the chat log that is not an error.
+    Notice(String),
     Error(String),
     Disconnected,
 }
 
 #[derive(Debug)]
 pub enum NetCommand {
-    SendMessage(String),
+    ListKeys,
+    RevokeKey {
+        pubkey: String,
+    },
+    /// Retries enrollment on a connection whose login was refused.
+    Enroll {
+        code: String,
+    },
+    SendMessage {
+        text: String,
+        reply_to: Option<String>,
+    },
     SendAdminCmd(String),
+    Admin {
+        req_id: u64,
+        action: String,
+        args: serde_json::Map<String, serde_json::Value>,
+    },
+    SendDirect {
+        to: String,
+        text: String,
+    },
+    EditMessage {
+        id: String,
+        text: String,
+    },
+    DeleteMessage {
+        id: String,
+    },
+    React {
+        id: String,
+        emoji: String,
+        remove: bool,
+    },
+    Typing(bool),
+    SetPresence {
+        state: String,
+        status: Option<String>,
+    },
+}
+
+impl NetCommand {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ListKeys => "ListKeys",
+            Self::RevokeKey { .. } => "RevokeKey",
+            Self::Enroll { .. } => "Enroll",
+            Self::SendMessage { .. } => "SendMessage",
+            Self::SendAdminCmd(_) => "SendAdminCmd",
+            Self::Admin { .. } => "Admin",
+            Self::SendDirect { .. } => "SendDirect",
+            Self::EditMessage { .. } => "EditMessage",
+            Self::DeleteMessage { .. } => "DeleteMessage",
+            Self::React { .. } => "React",
+            Self::Typing(_) => "Typing",
+            Self::SetPresence { .. } => "SetPresence",
+        }
+    }
 }
 
 fn build_tls_config(insecure: bool) -> Result<Arc<rustls::ClientConfig>> {
@@ -172,6 +420,92 @@ pub struct NetParams {
     pub insecure: bool,
 }
 
+async fn dial(server: &str, insecure: bool) -> Result<TlsStream<TcpStream>> {
+    let host = server
+        .split(':')
+        .next()
+        .context("Invalid server address")?
+        .to_string();
+
+    let tls_config = build_tls_config(insecure)?;
+    let connector = TlsConnector::from(tls_config);
+    let server_name = ServerName::try_from(host.clone())
+        .map_err(|_| anyhow::anyhow!("Invalid hostname '{}' — use host:port format", host))?;
+
+    let tcp = TcpStream::connect(server)
+        .await
+        .map_err(|e| anyhow::anyhow!("Cannot connect to '{}': {}", server, e))?;
+
+    connector
+        .connect(server_name, tcp)
+        .await
+        .map_err(|e| anyhow::anyhow!("TLS handshake failed (try --insecure?): {}", e))
+}
+
+/// A logged-in connection for one-shot requests made outside the chat UI.
+pub struct Link {
+    reader: BufReader<ReadHalf<TlsStream<TcpStream>>>,
+    writer: WriteHalf<TlsStream<TcpStream>>,
+}
+
+impl Link {
+    pub async fn login(server: &str, insecure: bool, username: &str, key: &SigningKey) -> Result<Self> {
+        let (reader, writer) = tokio::io::split(dial(server, insecure).await?);
+        let mut link = Self { reader: BufReader::new(reader), writer };
+
+        let challenge: Challenge = serde_json::from_value(link.recv().await?)
+            .context("Expected challenge from server")?;
+        let auth = AuthMsg {
+            msg_type: "auth".into(),
+            pubkey: crate::crypto::pubkey_b64(key),
+            username: username.to_string(),
+            sig: crate::crypto::sign_nonce(key, &challenge.nonce)?,
+        };
+        link.send(&auth).await?;
+
+        let resp: AuthResponse = serde_json::from_value(link.recv().await?)
+            .context("Expected auth response")?;
+        if resp.msg_type != "auth_ok" {
+            bail!("Login refused: {}", resp.reason.unwrap_or_else(|| "auth_fail".into()));
+        }
+        Ok(link)
+    }
+
+    async fn send<T: Serialize>(&mut self, msg: &T) -> Result<()> {
+        write_line(&mut self.writer, msg).await
+    }
+
+    async fn recv(&mut self) -> Result<serde_json::Value> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line).await? == 0 {
+                bail!("Server closed the connection");
+            }
+            if !line.trim().is_empty() {
+                return Ok(serde_json::from_str(line.trim())?);
+            }
+        }
+    }
+
+    /// Sends `msg` and waits for a reply of one of the `expect` types, skipping
+    /// chat traffic. An `error` reply becomes an `Err`.
+    pub async fn request<T: Serialize>(&mut self, msg: &T, expect: &[&str]) -> Result<serde_json::Value> {
+        self.send(msg).await?;
+        loop {
+            let reply = self.recv().await?;
+            let kind = reply.get("type").and_then(|t| t.as_str()).unwrap_or_default();
+            if kind == "error" {
+                let reason = reply.get("reason").and_then(|r| r.as_str()).unwrap_or("request failed");
+                return Err(Refused(reason.to_string()).into());
+            }
+            if expect.contains(&kind) {
+                return Ok(reply);
+            }
+        }
+    }
+}
+
 pub async fn connect(
     params: NetParams,
     event_tx: mpsc::UnboundedSender<NetEvent>,
@@ -190,26 +524,7 @@ async fn run_session(
     event_tx: mpsc::UnboundedSender<NetEvent>,
     cmd_rx: &mut mpsc::UnboundedReceiver<NetCommand>,
 ) -> Result<()> {
-    let host = params
-        .server
-        .split(':')
-        .next()
-        .context("Invalid server address")?
-        .to_string();
-
-    let tls_config = build_tls_config(params.insecure)?;
-    let connector = TlsConnector::from(tls_config);
-    let server_name = ServerName::try_from(host.clone())
-        .map_err(|_| anyhow::anyhow!("Invalid hostname '{}' — use host:port format", host))?;
-
-    let tcp = TcpStream::connect(&params.server)
-        .await
-        .map_err(|e| anyhow::anyhow!("Cannot connect to '{}': {}", params.server, e))?;
-
-    let stream = connector
-        .connect(server_name, tcp)
-        .await
-        .map_err(|e| anyhow::anyhow!("TLS handshake failed (try --insecure?): {}", e))?;
+    let stream = dial(&params.server, params.insecure).await?;
 
     let _ = event_tx.send(NetEvent::Connected);
 
@@ -227,40 +542,59 @@ async fn run_session(
     }
 
     let username = params.username.clone();
-    if let Some(invite_code) = &params.enroll_code {
-        let msg = EnrollMsg {
-            msg_type: "enroll".into(),
-            username: params.username.clone(),
-            pubkey: params.pubkey_b64.clone(),
-            invite_code: invite_code.clone(),
-        };
-        let mut out = serde_json::to_vec(&msg)?;
-        out.push(b'\n');
-        writer.write_all(&out).await?;
-    } else {
-        let sig = (params.sig_fn)(&challenge.nonce)?;
-        let auth = AuthMsg {
-            msg_type: "auth".into(),
-            pubkey: params.pubkey_b64.clone(),
-            username: params.username.clone(),
-            sig,
-        };
-        let mut out = serde_json::to_vec(&auth)?;
-        out.push(b'\n');
-        writer.write_all(&out).await?;
-    }
+    let mut enroll_code = params.enroll_code.clone();
+    loop {
+        match &enroll_code {
+            Some(invite_code) => write_line(&mut writer, &EnrollMsg {
+                msg_type: "enroll".into(),
+                username: params.username.clone(),
+                pubkey: params.pubkey_b64.clone(),
+                invite_code: invite_code.clone(),
+            }).await?,
+            None => write_line(&mut writer, &AuthMsg {
+                msg_type: "auth".into(),
+                pubkey: params.pubkey_b64.clone(),
+                username: params.username.clone(),
+                sig: (params.sig_fn)(&challenge.nonce)?,
+            }).await?,
+        }
 
-    line.clear();
-    reader.read_line(&mut line).await?;
-    let resp: AuthResponse = serde_json::from_str(line.trim())
-        .context("Expected auth response")?;
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            bail!("Server closed the connection during login");
+        }
+        let resp: AuthResponse = serde_json::from_str(line.trim())
+            .context("Expected auth response")?;
 
-    if resp.msg_type == "auth_ok" {
-        let _ = event_tx.send(NetEvent::AuthOk { username: username.clone() });
-    } else {
+        if resp.msg_type == "auth_ok" {
+            let is_admin = resp.is_admin || resp.role.as_deref() == Some("admin");
+            let _ = event_tx.send(NetEvent::AuthOk { username: username.clone(), is_admin });
+            break;
+        }
         let reason = resp.reason.unwrap_or_else(|| "auth_fail".into());
+        let reason = AuthFailure::classify(enroll_code.is_some(), resp.code.as_deref(), &reason);
         let _ = event_tx.send(NetEvent::AuthFail { reason });
-        return Ok(());
+
+        // Keep the connection open for another invite code, unless the server hangs up.
+        enroll_code = Some(loop {
+            line.clear();
+            tokio::select! {
+                result = reader.read_line(&mut line) => {
+                    if result? == 0 { return Ok(()); }
+                    let kind = serde_json::from_str::<serde_json::Value>(line.trim()).ok()
+                        .and_then(|v| v.get("type")?.as_str().map(str::to_string))
+                        .unwrap_or_else(|| "unrecognised".into());
+                    let _ = event_tx.send(NetEvent::Notice(format!("Ignored {kind} message from server before login")));
+                }
+                cmd = cmd_rx.recv() => match cmd {
+                    Some(NetCommand::Enroll { code }) => break code,
+                    Some(other) => {
+                        let _ = event_tx.send(NetEvent::Notice(format!("Dropped {} sent before login", other.name())));
+                    }
+                    None => return Ok(()),
+                },
+            }
+        });
     }
 
     loop {
@@ -278,11 +612,24 @@ async fn run_session(
                 };
 
                 match server_msg.msg_type.as_str() {
-                    "msg" | "presence" => { let _ = event_tx.send(NetEvent::Message(Box::new(server_msg))); }
+                    "msg" | "dm" | "presence" | "status" | "edit" | "delete" | "reaction" => { let _ = event_tx.send(NetEvent::Message(Box::new(server_msg))); }
+                    "typing" | "typing_stop" => {
+                        if let Some(user) = server_msg.from {
+                            let active = server_msg.msg_type == "typing";
+                            let _ = event_tx.send(NetEvent::Typing { user, active });
+                        }
+                    }
+                    "key_list" => {
+                        let keys = server_msg.data
+                            .and_then(|d| serde_json::from_value(d).ok())
+                            .unwrap_or_default();
+                        let _ = event_tx.send(NetEvent::DeviceKeys(keys));
+                    }
                     "admin_res" => {
                         let _ = event_tx.send(NetEvent::AdminResponse {
                             action: server_msg.action.unwrap_or_default(),
                             data: server_msg.data.unwrap_or_default(),
+                            req_id: server_msg.req_id,
                         });
                     }
                     _ => {}
@@ -290,19 +637,44 @@ async fn run_session(
             }
 
             cmd = cmd_rx.recv() => {
+                let w = &mut writer;
                 match cmd {
-                    Some(NetCommand::SendMessage(text)) => {
-                        let m = ClientMsg { msg_type: "msg".into(), text };
-                        let mut out = serde_json::to_vec(&m)?;
-                        out.push(b'\n');
-                        writer.write_all(&out).await?;
+                    Some(NetCommand::SendMessage { text, reply_to }) => {
+                        write_line(w, &ClientMsg { msg_type: "msg".into(), text, reply_to }).await?;
                     }
                     Some(NetCommand::SendAdminCmd(action)) => {
-                        let m = serde_json::json!({ "type": "admin_cmd", "action": action });
-                        let mut out = serde_json::to_vec(&m)?;
-                        out.push(b'\n');
-                        writer.write_all(&out).await?;
+                        write_line(w, &AdminCmdMsg { msg_type: "admin_cmd".into(), action, args: None, req_id: None }).await?;
+                    }
+                    Some(NetCommand::Admin { req_id, action, args }) => {
+                        let msg = AdminCmdMsg { msg_type: "admin_cmd".into(), action, args: Some(args), req_id: Some(req_id) };
+                        write_line(w, &msg).await?;
+                    }
+                    Some(NetCommand::SendDirect { to, text }) => {
+                        write_line(w, &DirectMsg { msg_type: "dm".into(), to, text }).await?;
+                    }
+                    Some(NetCommand::EditMessage { id, text }) => {
+                        write_line(w, &EditMsg { msg_type: "edit".into(), id, text }).await?;
+                    }
+                    Some(NetCommand::DeleteMessage { id }) => {
+                        write_line(w, &DeleteMsg { msg_type: "delete".into(), id }).await?;
+                    }
+                    Some(NetCommand::React { id, emoji, remove }) => {
+                        write_line(w, &ReactionMsg { msg_type: "reaction".into(), id, emoji, remove }).await?;
+                    }
+                    Some(NetCommand::Typing(active)) => {
+                        let kind = if active { "typing" } else { "typing_stop" };
+                        write_line(w, &BareMsg { msg_type: kind.into() }).await?;
+                    }
+                    Some(NetCommand::SetPresence { state, status }) => {
+                        write_line(w, &StatusMsg { msg_type: "status".into(), state, status }).await?;
+                    }
+                    Some(NetCommand::ListKeys) => {
+                        write_line(w, &BareMsg { msg_type: "key_list".into() }).await?;
+                    }
+                    Some(NetCommand::RevokeKey { pubkey }) => {
+                        write_line(w, &KeyRevokeMsg { msg_type: "key_revoke".into(), pubkey }).await?;
                     }
+                    Some(NetCommand::Enroll { .. }) => {}
                     None => break,
                 }
             }
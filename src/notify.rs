@@ -1,51 +1,373 @@
-use std::io::Cursor;
+use futures::StreamExt;
 use rodio::{Decoder, Sink, OutputStreamBuilder};
+use std::collections::HashMap;
+use std::io::{Cursor, Write};
+use std::sync::{mpsc as std_mpsc, Arc};
+use tokio::sync::mpsc;
+use zbus::zvariant::Value;
+
+use crate::config::SoundPrefs;
 
 static NOTIF_BYTES: &[u8] = include_bytes!("../assets/notif.mp3");
 
-fn with_stderr_suppressed<F: FnOnce()>(f: F) {
+fn with_stderr_suppressed<T, F: FnOnce() -> T>(f: F) -> T {
     #[cfg(unix)]
     unsafe {
-        use std::ffi::CString;
-        let path = CString::new("/dev/null").unwrap();
-        let devnull = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        let devnull = libc::open(c"/dev/null".as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
         if devnull < 0 {
-            f();
-            return;
+            return f();
         }
         let saved = libc::dup(2);
         libc::dup2(devnull, 2);
         libc::close(devnull);
 
-        f();
+        let out = f();
 
         if saved >= 0 {
             libc::dup2(saved, 2);
             libc::close(saved);
         }
+        out
     }
     #[cfg(not(unix))]
-    f();
+    f()
+}
+
+/// Ordered by priority: when a burst is coalesced, the highest one wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SoundEvent {
+    Join,
+    Message,
+    Mention,
+    DirectMessage,
+}
+
+enum AudioCmd {
+    Play(SoundEvent),
+    Configure(SoundPrefs),
+}
+
+pub struct AudioPlayer {
+    tx: std_mpsc::Sender<AudioCmd>,
+}
+
+impl AudioPlayer {
+    pub fn spawn(prefs: SoundPrefs) -> Self {
+        let (tx, rx) = std_mpsc::channel();
+        let _ = std::thread::Builder::new()
+            .name("ttychat-audio".into())
+            .spawn(move || audio_worker(rx, prefs));
+        Self { tx }
+    }
+
+    pub fn play(&self, event: SoundEvent) {
+        let _ = self.tx.send(AudioCmd::Play(event));
+    }
+
+    pub fn configure(&self, prefs: SoundPrefs) {
+        let _ = self.tx.send(AudioCmd::Configure(prefs));
+    }
 }
 
-pub fn play_notification_sound() {
-    with_stderr_suppressed(|| {
-        let Ok(stream) = OutputStreamBuilder::open_default_stream() else { return };
-        let sink = Sink::connect_new(&stream.mixer());
-        let cursor = Cursor::new(NOTIF_BYTES);
-        let Ok(source) = Decoder::new(cursor) else { return };
+fn audio_worker(rx: std_mpsc::Receiver<AudioCmd>, mut prefs: SoundPrefs) {
+    let stream = with_stderr_suppressed(|| OutputStreamBuilder::open_default_stream().ok());
+    let sink = stream.as_ref().map(|s| Sink::connect_new(s.mixer()));
+    let mut cache: HashMap<SoundEvent, Arc<[u8]>> = HashMap::new();
+    let mut playing: Option<SoundEvent> = None;
+
+    while let Ok(cmd) = rx.recv() {
+        let mut next = None;
+        let mut apply = |cmd: AudioCmd, next: &mut Option<SoundEvent>| match cmd {
+            AudioCmd::Play(ev) => *next = (*next).max(Some(ev)),
+            AudioCmd::Configure(p) => {
+                prefs = p;
+                cache.clear();
+            }
+        };
+        apply(cmd, &mut next);
+        while let Ok(cmd) = rx.try_recv() {
+            apply(cmd, &mut next);
+        }
+
+        let Some(sink) = &sink else {
+            if next.is_some() {
+                write_raw(b"\x07");
+            }
+            continue;
+        };
+        sink.set_volume(prefs.volume.clamp(0.0, 2.0));
+
+        let Some(event) = next else { continue };
+        if sink.empty() {
+            playing = None;
+        }
+        if playing.is_some_and(|p| p >= event) {
+            continue;
+        }
+
+        let bytes = cache
+            .entry(event)
+            .or_insert_with(|| load_sound(&prefs, event))
+            .clone();
+        let Ok(source) = Decoder::new(Cursor::new(bytes)) else { continue };
+        sink.clear();
         sink.append(source);
-        sink.sleep_until_end();
+        sink.play();
+        playing = Some(event);
+    }
+}
+
+fn load_sound(prefs: &SoundPrefs, event: SoundEvent) -> Arc<[u8]> {
+    let path = match event {
+        SoundEvent::Join => &prefs.join,
+        SoundEvent::Message => &prefs.message,
+        SoundEvent::Mention => &prefs.mention,
+        SoundEvent::DirectMessage => &prefs.dm,
+    };
+    path.as_ref()
+        .and_then(|p| read_sound(p).ok())
+        .unwrap_or_else(|| Arc::from(NOTIF_BYTES))
+}
+
+/// Reads a sound file and makes sure it decodes.
+pub fn read_sound(path: &std::path::Path) -> Result<Arc<[u8]>, String> {
+    let bytes: Arc<[u8]> = std::fs::read(path).map_err(|e| e.to_string())?.into();
+    Decoder::new(Cursor::new(bytes.clone())).map_err(|e| e.to_string())?;
+    Ok(bytes)
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: String) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
+}
+
+#[derive(Debug, Clone)]
+pub struct DesktopNotification {
+    pub from: String,
+    pub text: String,
+}
+
+pub fn spawn_desktop_notifier() -> mpsc::UnboundedSender<DesktopNotification> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        match connect_bus().await {
+            Ok(bus) => run_dbus_notifier(bus, rx).await,
+            Err(_) => run_terminal_notifier(rx).await,
+        }
     });
+    tx
+}
+
+struct Bus {
+    proxy: NotificationsProxy<'static>,
+    actions: ActionInvokedStream,
+    closed: NotificationClosedStream,
+}
+
+async fn connect_bus() -> zbus::Result<Bus> {
+    Bus::new(&zbus::Connection::session().await?).await
 }
 
-pub fn send_desktop_notification(from: &str, text: &str) {
-    let body: String = text.chars().take(120).collect();
-    let _ = std::process::Command::new("notify-send")
-        .arg("--app-name=ttychat")
-        .arg("--urgency=normal")
-        .arg("--expire-time=5000")
-        .arg(format!("ttychat ✉ {from}"))
-        .arg(body)
-        .spawn();
+impl Bus {
+    async fn new(conn: &zbus::Connection) -> zbus::Result<Self> {
+        let proxy = NotificationsProxy::new(conn).await?;
+        let actions = proxy.receive_action_invoked().await?;
+        let closed = proxy.receive_notification_closed().await?;
+        Ok(Self { proxy, actions, closed })
+    }
+}
+
+async fn run_dbus_notifier(mut bus: Bus, mut rx: mpsc::UnboundedReceiver<DesktopNotification>) {
+    let mut by_sender: HashMap<String, u32> = HashMap::new();
+
+    loop {
+        tokio::select! {
+            req = rx.recv() => {
+                let Some(n) = req else { break };
+                let replaces_id = by_sender.get(&n.from).copied().unwrap_or(0);
+                let summary = format!("ttychat ✉ {}", n.from);
+                let body = truncate_body(&n.text);
+                let hints = HashMap::from([("urgency", Value::U8(1))]);
+                let result = bus.proxy.notify(
+                    "ttychat",
+                    replaces_id,
+                    "",
+                    &summary,
+                    &body,
+                    &["default", "Open", "focus", "Focus terminal"],
+                    hints,
+                    5000,
+                ).await;
+                match result {
+                    Ok(id) => { by_sender.insert(n.from, id); }
+                    Err(_) => send_terminal_notification(&n.from, &n.text),
+                }
+            }
+            Some(sig) = bus.actions.next() => {
+                let Ok(args) = sig.args() else { continue };
+                let ours = by_sender.values().any(|&id| id == args.id);
+                if ours && matches!(args.action_key.as_str(), "default" | "focus") {
+                    focus_terminal();
+                }
+            }
+            Some(sig) = bus.closed.next() => {
+                let Ok(args) = sig.args() else { continue };
+                by_sender.retain(|_, id| *id != args.id);
+            }
+        }
+    }
+}
+
+async fn run_terminal_notifier(mut rx: mpsc::UnboundedReceiver<DesktopNotification>) {
+    while let Some(n) = rx.recv().await {
+        send_terminal_notification(&n.from, &n.text);
+    }
+}
+
+fn truncate_body(text: &str) -> String {
+    text.chars().take(120).collect()
+}
+
+fn sanitize_osc(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control() && *c != ';').collect()
+}
+
+fn send_terminal_notification(from: &str, text: &str) {
+    let term = std::env::var("TERM").unwrap_or_default();
+    let vte = std::env::var_os("VTE_VERSION").is_some();
+    write_raw(terminal_notification(from, text, &term, vte).as_bytes());
+}
+
+/// OSC 777 is understood by urxvt, foot and VTE; most others (iTerm2, WezTerm,
+/// Windows Terminal, kitty, ghostty) take OSC 9.
+fn terminal_notification(from: &str, text: &str, term: &str, vte: bool) -> String {
+    let title = sanitize_osc(&format!("ttychat ✉ {from}"));
+    let body = sanitize_osc(&truncate_body(text));
+    if term.starts_with("rxvt") || term.starts_with("foot") || vte {
+        format!("\x1b]777;notify;{title};{body}\x07")
+    } else {
+        format!("\x1b]9;{title}: {body}\x07")
+    }
+}
+
+/// XTWINOPS de-iconify + raise. Only xterm honours this, and only with
+/// `allowWindowOps` enabled; most other terminals, tmux and screen drop it, so
+/// "Focus terminal" may do nothing. There is no portable way for a program
+/// inside a terminal to raise its window.
+fn focus_terminal() {
+    write_raw(b"\x1b[1t\x1b[5t");
+}
+
+fn write_raw(bytes: &[u8]) {
+    let mut out = std::io::stdout().lock();
+    let _ = out.write_all(bytes);
+    let _ = out.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct FakeServer {
+        calls: Arc<Mutex<Vec<(String, u32)>>>,
+        next_id: u32,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.Notifications")]
+    impl FakeServer {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &mut self,
+            _app_name: &str,
+            replaces_id: u32,
+            _app_icon: &str,
+            summary: &str,
+            _body: &str,
+            _actions: Vec<&str>,
+            _hints: HashMap<&str, Value<'_>>,
+            _expire_timeout: i32,
+        ) -> u32 {
+            self.calls.lock().unwrap().push((summary.to_string(), replaces_id));
+            if replaces_id != 0 {
+                return replaces_id;
+            }
+            self.next_id += 1;
+            self.next_id
+        }
+    }
+
+    #[tokio::test]
+    async fn reuses_replace_id_per_sender() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let (client_end, server_end) = tokio::net::UnixStream::pair().unwrap();
+        let guid = zbus::Guid::generate();
+        let server = zbus::connection::Builder::unix_stream(server_end)
+            .server(guid)
+            .unwrap()
+            .p2p()
+            .serve_at("/org/freedesktop/Notifications", FakeServer { calls: calls.clone(), next_id: 0 })
+            .unwrap()
+            .build();
+        let client = zbus::connection::Builder::unix_stream(client_end).p2p().build();
+        let (_server, client) = tokio::try_join!(server, client).unwrap();
+
+        let bus = Bus::new(&client).await.unwrap();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let task = tokio::spawn(run_dbus_notifier(bus, rx));
+        for from in ["alice", "bob", "alice"] {
+            tx.send(DesktopNotification { from: from.into(), text: "hi".into() }).unwrap();
+        }
+        drop(tx);
+        task.await.unwrap();
+
+        let calls = calls.lock().unwrap();
+        let replaces: Vec<u32> = calls.iter().map(|(_, id)| *id).collect();
+        assert_eq!(replaces, [0, 0, 1]);
+        assert_eq!(calls[2].0, "ttychat ✉ alice");
+    }
+
+    #[test]
+    fn osc_fallback_picks_sequence_by_terminal() {
+        assert_eq!(
+            terminal_notification("bob", "hi", "foot", false),
+            "\x1b]777;notify;ttychat ✉ bob;hi\x07"
+        );
+        assert_eq!(
+            terminal_notification("bob", "hi", "xterm-256color", true),
+            "\x1b]777;notify;ttychat ✉ bob;hi\x07"
+        );
+        assert_eq!(
+            terminal_notification("bob", "hi", "xterm-kitty", false),
+            "\x1b]9;ttychat ✉ bob: hi\x07"
+        );
+    }
+
+    #[test]
+    fn osc_fallback_strips_control_characters() {
+        let seq = terminal_notification("eve", "a\x07;b\x1b]9;x", "xterm", false);
+        assert_eq!(seq, "\x1b]9;ttychat ✉ eve: ab]9x\x07");
+    }
 }
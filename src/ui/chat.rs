@@ -3,14 +3,16 @@ use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
     widgets::{
-        Block, Borders, Clear, List, ListItem, Paragraph, Scrollbar, ScrollbarOrientation,
-        ScrollbarState,
+        Block, Borders, Clear, List, ListItem, ListState, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState,
     },
     Frame,
 };
 
-use crate::app::App;
-use crate::widgets::messages::{render_message, user_color_simple};
+use crate::app::verify::Trust;
+use crate::app::{App, ChatMessage, PresenceState, UserAction, UserPopup};
+use crate::keymap::Action;
+use crate::widgets::messages::{layout_messages, user_color_simple};
 
 pub fn draw_chat(frame: &mut Frame, app: &App) {
     let area = frame.area();
@@ -30,9 +32,67 @@ pub fn draw_chat(frame: &mut Frame, app: &App) {
     draw_input_bar(frame, app, rows[2]);
     draw_hints_bar(frame, app, rows[3]);
 
-    if app.chat.show_help {
+    if app.chat.confirm_delete.is_some() {
+        draw_delete_confirm(frame, area);
+    } else if app.chat.show_help {
         draw_help_overlay(frame, area, app);
+    } else if let Some(msg) = app.chat.detail.and_then(|i| app.chat.messages.get(i)) {
+        draw_detail_popup(frame, area, msg);
+    } else if let Some((_, cursor)) = app.chat.reaction_picker {
+        draw_reaction_picker(frame, rows[2], cursor);
+    } else if let Some(popup) = &app.chat.user_popup {
+        draw_user_popup(frame, area, app, popup);
+    }
+}
+
+fn draw_delete_confirm(frame: &mut Frame, area: Rect) {
+    let overlay = crate::ui::centered_rect(area, 46, 6);
+    frame.render_widget(Clear, overlay);
+    let lines = vec![
+        Line::from(""),
+        Line::from(Span::styled("  Delete this message for everyone?", Style::default().fg(Color::White).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from(Span::styled("  [ y = delete  |  n / Esc = keep ]", Style::default().fg(Color::DarkGray))),
+    ];
+    let popup = Paragraph::new(Text::from(lines)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(ratatui::widgets::BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Red))
+            .title(Span::styled(" DELETE ", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))),
+    );
+    frame.render_widget(popup, overlay);
+}
+
+const QUICK_REACTIONS: [&str; 8] = ["👍", "👎", "😂", "🎉", "😮", "😢", "🔥", "💯"];
+
+fn draw_reaction_picker(frame: &mut Frame, input_area: Rect, cursor: usize) {
+    let mut spans = vec![Span::raw(" ")];
+    for (i, emoji) in QUICK_REACTIONS.iter().enumerate() {
+        let style = if i == cursor {
+            Style::default().bg(Color::Cyan).fg(Color::Black)
+        } else {
+            Style::default()
+        };
+        spans.push(Span::styled(format!(" {emoji} "), style));
     }
+
+    let width = (QUICK_REACTIONS.len() as u16 * 4 + 4).min(input_area.width);
+    let popup = Rect {
+        x: input_area.x + 1,
+        y: input_area.y.saturating_sub(3),
+        width,
+        height: 3,
+    };
+    frame.render_widget(Clear, popup);
+    let picker = Paragraph::new(Line::from(spans)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(ratatui::widgets::BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Cyan))
+            .title(Span::styled(" REACT ", Style::default().fg(Color::Cyan))),
+    );
+    frame.render_widget(picker, popup);
 }
 
 fn draw_title_bar(frame: &mut Frame, app: &App, area: Rect) {
@@ -55,10 +115,46 @@ fn draw_title_bar(frame: &mut Frame, app: &App, area: Rect) {
         Span::styled(time, Style::default().fg(Color::Cyan)),
     ]);
 
+    let tabs = app.session_tabs();
+    let title_text = if tabs.len() > 1 {
+        session_switcher(&tabs)
+    } else {
+        title_text
+    };
+
     let title = Paragraph::new(title_text);
     frame.render_widget(title, area);
 }
 
+fn session_switcher(tabs: &[crate::app::session::SessionTab]) -> Line<'static> {
+    let mut spans = vec![Span::styled(" TTYCHAT ", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))];
+    for (i, tab) in tabs.iter().enumerate().take(9) {
+        spans.push(Span::styled(" │ ", Style::default().fg(Color::DarkGray)));
+        let style = if tab.active {
+            Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD)
+        } else if !tab.online {
+            Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+        spans.push(Span::styled(format!(" {} {} ", i + 1, tab.server), style));
+        if tab.unread > 0 {
+            spans.push(Span::styled(
+                format!(" {}", tab.unread),
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            ));
+        }
+    }
+    let total: u32 = tabs.iter().map(|t| t.unread).sum();
+    if total > 0 {
+        spans.push(Span::styled(
+            format!("  │  {total} unread elsewhere (Alt+1..9)"),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    Line::from(spans)
+}
+
 fn draw_main_content(frame: &mut Frame, app: &App, area: Rect) {
     let cols = Layout::default()
         .direction(Direction::Horizontal)
@@ -73,17 +169,12 @@ fn draw_main_content(frame: &mut Frame, app: &App, area: Rect) {
 }
 
 fn draw_messages(frame: &mut Frame, app: &App, area: Rect) {
-    let msgs = &app.chat.messages;
-    let username = &app.chat.username;
-
-    let mut all_lines = Vec::new();
     let inner_width = area.width.saturating_sub(2) as usize;
-    for msg in msgs {
-        all_lines.extend(render_message(msg, username, inner_width));
-    }
-    
-    let total_rows = all_lines.len();
     let visible_height = area.height.saturating_sub(2) as usize;
+    app.chat.msg_view.set((inner_width, visible_height));
+
+    let all_lines = layout_messages(&app.chat, &app.config.timestamp, inner_width).lines;
+    let total_rows = all_lines.len();
 
     let max_scroll = total_rows.saturating_sub(visible_height);
     let scroll = app.chat.scroll_offset.min(max_scroll);
@@ -151,10 +242,32 @@ fn draw_messages(frame: &mut Frame, app: &App, area: Rect) {
         };
         frame.render_widget(indicator, indicator_area);
     }
+
+    if scroll > 0 && app.chat.new_below > 0 {
+        let n = app.chat.new_below;
+        let label = if n == 1 { "new message".to_string() } else { format!("{n} new messages") };
+        let badge = Paragraph::new(Line::from(Span::styled(
+            format!(" {label} ↓ "),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::LightRed)
+                .add_modifier(Modifier::BOLD),
+        )))
+        .alignment(Alignment::Center);
+
+        let badge_area = Rect {
+            x: area.x + 1,
+            y: area.y + area.height.saturating_sub(2),
+            width: area.width.saturating_sub(2),
+            height: 1,
+        };
+        frame.render_widget(badge, badge_area);
+    }
 }
 
 fn draw_user_list(frame: &mut Frame, app: &App, area: Rect) {
     let focused = app.chat.focus_users;
+    let mut selected_item = None;
 
     let items: Vec<ListItem> = if app.chat.online_users.is_empty() {
         vec![ListItem::new(Line::from(Span::styled(
@@ -162,27 +275,63 @@ fn draw_user_list(frame: &mut Frame, app: &App, area: Rect) {
             Style::default().fg(Color::Rgb(40, 50, 40)),
         )))]
     } else {
-        app.chat
-            .online_users
-            .iter()
-            .map(|u| {
-                let is_self = u == &app.chat.username;
-                let color = if is_self { Color::Cyan } else { user_color_simple(u) };
-                let prefix = if is_self { "▶ " } else { "  " };
-                ListItem::new(Line::from(vec![
-                    Span::styled(
-                        format!("{prefix}● "),
-                        Style::default().fg(color).add_modifier(Modifier::DIM),
-                    ),
-                    Span::styled(
-                        u.as_str(),
-                        Style::default()
-                            .fg(color)
-                            .add_modifier(if is_self { Modifier::BOLD } else { Modifier::empty() }),
-                    ),
-                ]))
-            })
-            .collect()
+        let users = app.chat.sorted_users();
+        let grouped = users.iter()
+            .any(|u| app.chat.presence_of(u).state != PresenceState::Online);
+        let inner_width = area.width.saturating_sub(2) as usize;
+        let cursor = app.chat.user_cursor.min(users.len() - 1);
+        let mut items = Vec::new();
+        let mut last_state = None;
+
+        for (i, u) in users.into_iter().enumerate() {
+            let presence = app.chat.presence_of(u);
+            if grouped && last_state != Some(presence.state) {
+                last_state = Some(presence.state);
+                items.push(ListItem::new(Line::from(Span::styled(
+                    format!(" {}", presence.state.as_str().to_uppercase()),
+                    Style::default().fg(Color::DarkGray).add_modifier(Modifier::BOLD),
+                ))));
+            }
+            if i == cursor {
+                selected_item = Some(items.len());
+            }
+
+            let is_self = u == app.chat.username;
+            let color = if is_self { Color::Cyan } else { user_color_simple(u) };
+            let prefix = if is_self { "▶ " } else { "  " };
+            let mut name_style = Style::default()
+                .fg(color)
+                .add_modifier(if is_self { Modifier::BOLD } else { Modifier::empty() });
+            if presence.state == PresenceState::Idle {
+                name_style = name_style.add_modifier(Modifier::DIM);
+            }
+
+            let mut name_line = vec![
+                Span::raw(prefix),
+                Span::styled("● ", Style::default().fg(presence_color(presence.state))),
+                Span::styled(u.to_string(), name_style),
+            ];
+            match app.trust_of(u) {
+                Trust::Verified => name_line.push(Span::styled(" ✔", Style::default().fg(Color::Green))),
+                Trust::Changed => name_line.push(Span::styled(" ⚠", Style::default().fg(Color::Red))),
+                Trust::Unknown | Trust::Unverified => {}
+            }
+            let mut lines = vec![Line::from(name_line)];
+            let detail = presence.status.clone().or_else(|| {
+                presence.since
+                    .filter(|_| presence.state != PresenceState::Online)
+                    .map(|t| format!("{} {}", presence.state.as_str(), short_age(t)))
+            });
+            if let Some(detail) = detail {
+                let text: String = detail.chars().take(inner_width.saturating_sub(5)).collect();
+                lines.push(Line::from(Span::styled(
+                    format!("    {text}"),
+                    Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+                )));
+            }
+            items.push(ListItem::new(Text::from(lines)));
+        }
+        items
     };
 
     let border_color = if focused { Color::Cyan } else { Color::DarkGray };
@@ -198,9 +347,29 @@ fn draw_user_list(frame: &mut Frame, app: &App, area: Rect) {
                     format!(" ONLINE ({user_count}) "),
                     Style::default().fg(if focused { Color::Cyan } else { Color::Gray }),
                 )),
-        );
+        )
+        .highlight_style(Style::default().bg(Color::Rgb(40, 44, 60)));
 
-    frame.render_widget(list, area);
+    let mut state = ListState::default().with_selected(selected_item.filter(|_| focused));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn short_age(since: chrono::DateTime<chrono::Local>) -> String {
+    let age = chrono::Local::now().signed_duration_since(since);
+    if age.num_hours() > 0 {
+        format!("{}h", age.num_hours())
+    } else {
+        format!("{}m", age.num_minutes().max(1))
+    }
+}
+
+fn presence_color(state: PresenceState) -> Color {
+    match state {
+        PresenceState::Online => Color::Green,
+        PresenceState::Busy => Color::Red,
+        PresenceState::Away => Color::Yellow,
+        PresenceState::Idle => Color::DarkGray,
+    }
 }
 
 fn draw_input_bar(frame: &mut Frame, app: &App, area: Rect) {
@@ -240,7 +409,14 @@ fn draw_input_bar(frame: &mut Frame, app: &App, area: Rect) {
         ));
     }
 
-    let muted_indicator = if app.notifications_muted { "  muted" } else { "" };
+    let prefs = &app.notif_prefs;
+    let muted_indicator = if prefs.muted {
+        "  muted"
+    } else if prefs.is_quiet(chrono::Local::now().time()) {
+        "  quiet hours"
+    } else {
+        ""
+    };
     let placeholder = if input.is_empty() {
         vec![Span::styled(
             format!(" Type a message… (/mute · /unmute · /admin <action>){muted_indicator}"),
@@ -250,78 +426,179 @@ fn draw_input_bar(frame: &mut Frame, app: &App, area: Rect) {
         spans
     };
 
-    let para = Paragraph::new(Line::from(placeholder))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_type(ratatui::widgets::BorderType::Rounded)
-                .border_style(Style::default().fg(Color::Gray))
-                .title(Span::styled(
-                    " INPUT ",
-                    Style::default().fg(Color::Gray),
-                ))
-                .title_alignment(Alignment::Left),
-        );
+    let parent = app.chat.replying_to.as_deref()
+        .and_then(|id| app.chat.find_by_id(id))
+        .and_then(|i| app.chat.messages.get(i));
+    let title = match (&app.chat.replying_to, parent) {
+        _ if app.chat.editing.is_some() => Span::styled(
+            " EDITING (Enter saves · Esc cancels) ",
+            Style::default().fg(Color::Yellow),
+        ),
+        (Some(_), Some(p)) => Span::styled(
+            format!(" REPLY → {}: {} (Esc cancels) ", p.from, snippet(p.visible_text(), 24)),
+            Style::default().fg(Color::Cyan),
+        ),
+        (Some(_), None) => Span::styled(" REPLY (Esc cancels) ", Style::default().fg(Color::Cyan)),
+        (None, _) => Span::styled(" INPUT ", Style::default().fg(Color::Gray)),
+    };
+
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(ratatui::widgets::BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Gray))
+        .title(title)
+        .title_alignment(Alignment::Left);
+    if let Some(typing) = typing_text(app) {
+        block = block.title_bottom(Line::from(Span::styled(
+            format!(" {typing} "),
+            Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+        )));
+    }
+
+    let para = Paragraph::new(Line::from(placeholder)).block(block);
 
     frame.render_widget(para, area);
 }
 
-fn draw_hints_bar(frame: &mut Frame, _app: &App, area: Rect) {
+fn typing_text(app: &App) -> Option<String> {
+    let mut names: Vec<&str> = app.chat.typing.keys().map(String::as_str).collect();
+    names.sort_unstable();
+    match names.as_slice() {
+        [] => None,
+        [one] => Some(format!("{one} is typing…")),
+        [a, b] => Some(format!("{a} and {b} are typing…")),
+        many => Some(format!("{} people are typing…", many.len())),
+    }
+}
+
+fn draw_hints_bar(frame: &mut Frame, app: &App, area: Rect) {
+    if let Some((msg, until)) = &app.chat.flash {
+        if app.tick_count < *until {
+            let line = Line::from(Span::styled(
+                format!(" {msg}"),
+                Style::default().fg(Color::LightGreen).add_modifier(Modifier::BOLD),
+            ));
+            frame.render_widget(Paragraph::new(line), area);
+            return;
+        }
+    }
+
+    if let Some(pending) = app.keymap.pending_label() {
+        let line = Line::from(vec![hint_key(&format!("{pending} …")), hint_sep("  waiting for next key")]);
+        frame.render_widget(Paragraph::new(line), area);
+        return;
+    }
+
+    if app.chat.focus_users && app.chat.user_popup.is_none() {
+        let line = Line::from(vec![
+            hint_key("USERS"), hint_sep("  "),
+            hint_key("↑↓/jk"), hint_sep("move  "),
+            hint_key("Enter"), hint_sep("info  "),
+            hint_key("@"), hint_sep("mention  "),
+            hint_key("Tab/Esc"), hint_sep("back to input"),
+        ]);
+        frame.render_widget(Paragraph::new(line), area);
+        return;
+    }
+
+    if app.chat.selected.is_some() {
+        let line = Line::from(vec![
+            hint_key("SELECT"), hint_sep("  "),
+            hint_key("↑↓/jk"), hint_sep("move  "),
+            hint_key("y"), hint_sep("copy  "),
+            hint_key("Y"), hint_sep("copy quoted  "),
+            hint_key("r"), hint_sep("reply  "),
+            hint_key("e/d"), hint_sep("edit/del  "),
+            hint_key("+"), hint_sep("react  "),
+            hint_key("i"), hint_sep("details  "),
+            hint_key("Enter"), hint_sep("parent  "),
+            hint_key("Esc"), hint_sep("exit"),
+        ]);
+        frame.render_widget(Paragraph::new(line), area);
+        return;
+    }
+
     let line = Line::from(vec![
         hint_key("Enter"), hint_sep("send  "),
         hint_key("↑↓"), hint_sep("/"),
         hint_key("PgUp/Dn"), hint_sep("scroll  "),
         hint_key("Home/End"), hint_sep("top/bot  "),
-        hint_key("Tab"), hint_sep("focus  "),
-        hint_key("F1"), hint_sep("help  "),
-        hint_key("Ctrl-C"), hint_sep("quit"),
+        hint_key(&first_key(app, Action::JumpUnread)), hint_sep("new  "),
+        hint_key(&first_key(app, Action::SelectMode)), hint_sep("select  "),
+        hint_key(&first_key(app, Action::ToggleUsers)), hint_sep("focus  "),
+        hint_key(&first_key(app, Action::Help)), hint_sep("help  "),
+        hint_key(&first_key(app, Action::Quit)), hint_sep("quit"),
     ]);
 
     let bar = Paragraph::new(line);
     frame.render_widget(bar, area);
 }
 
-fn hint_key(s: &'static str) -> Span<'static> {
+fn first_key(app: &App, action: Action) -> String {
+    app.keymap.keys_for(action).into_iter().next().unwrap_or_else(|| "—".into())
+}
+
+fn hint_key(s: &str) -> Span<'static> {
     Span::styled(format!(" {s}"), Style::default().fg(Color::Gray).add_modifier(Modifier::BOLD))
 }
 fn hint_sep(s: &'static str) -> Span<'static> {
     Span::styled(s, Style::default().fg(Color::DarkGray))
 }
 
-fn draw_help_overlay(frame: &mut Frame, area: Rect, _app: &App) {
-    let overlay = Rect {
-        x: area.width.saturating_sub(48) / 2,
-        y: area.height.saturating_sub(26) / 2,
-        width: 48.min(area.width),
-        height: 26.min(area.height),
-    };
-
-    frame.render_widget(Clear, overlay);
-
-    let lines = vec![
+fn draw_help_overlay(frame: &mut Frame, area: Rect, app: &App) {
+    let mut lines = vec![
         Line::from(Span::styled("  KEYBINDS", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))),
         Line::from(""),
-        keybind_line("Enter",    "Send message"),
-        keybind_line("↑",       "Scroll up 1 line"),
-        keybind_line("↓",       "Scroll down 1 line"),
-        keybind_line("PgUp",    "Scroll up 10 lines"),
-        keybind_line("PgDn",    "Scroll down 10 lines"),
-        keybind_line("Home",    "Jump to oldest"),
-        keybind_line("End",     "Jump to latest"),
-        keybind_line("Tab",     "Focus input/userlist"),
-        keybind_line("Ctrl-U",  "Clear input"),
-        keybind_line("← / →",  "Move cursor"),
-        keybind_line("F1",      "Toggle this help"),
-        keybind_line("Ctrl-C",  "Quit"),
+    ];
+    for action in Action::ALL {
+        if matches!(action, Action::NextField | Action::PrevField) {
+            continue;
+        }
+        let keys = app.keymap.keys_for(action);
+        if !keys.is_empty() {
+            lines.push(keybind_line(&keys.join("/"), action.description()));
+        }
+    }
+    lines.push(keybind_line("← / →", "Move cursor"));
+    lines.extend([
         Line::from(""),
         Line::from(Span::styled("  COMMANDS", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))),
-        keybind_line("/mute",        "Mute notifications"),
-        keybind_line("/unmute",      "Unmute notifications"),
+        keybind_line("/mute [user]", "Mute notifications"),
+        keybind_line("/unmute [user]", "Unmute notifications"),
+        keybind_line("/notify",      "Sound/desktop/title toggles"),
+        keybind_line("/quiet",       "Quiet hours, e.g. 19:00-08:00"),
+        keybind_line("/volume",      "Notification volume (0-200)"),
+        keybind_line("/sound",       "Custom sound per event"),
+        keybind_line("/timefmt",     "12h/24h, seconds, relative"),
+        keybind_line("/edit [text]", "Edit your last message (or ↑)"),
+        keybind_line("/delete",      "Delete your last message"),
+        keybind_line("/react <emoji>", "Toggle reaction on latest message"),
+        keybind_line("/typing on|off", "Share your typing status"),
+        keybind_line("/away [msg]",  "Set away (also /busy)"),
+        keybind_line("/back",        "Set online again"),
+        keybind_line("/status <text>", "Set a short status"),
+        keybind_line("/joins on|off", "Show join/leave messages"),
+        keybind_line("/dm <user> <msg>", "Send a direct message"),
+        keybind_line("/ignore <user>", "Hide a user (/unignore)"),
+        keybind_line("/filters",     "Hide or dim by pattern"),
+        keybind_line("/keys [revoke]", "List or revoke device keys"),
+        keybind_line("/connect",     "Add another server (Alt+1..9)"),
+        keybind_line("/close",       "Disconnect this server"),
+        keybind_line("/admin",       "Open the admin console"),
         keybind_line("/admin invite","Request invite code"),
         Line::from(""),
-        Line::from(""),
         Line::from(Span::styled("  [ Any key = close ]", Style::default().fg(Color::DarkGray))),
-    ];
+    ]);
+
+    let height = lines.len() as u16 + 2;
+    let overlay = Rect {
+        x: area.width.saturating_sub(52) / 2,
+        y: area.height.saturating_sub(height) / 2,
+        width: 52.min(area.width),
+        height: height.min(area.height),
+    };
+
+    frame.render_widget(Clear, overlay);
 
     let help = Paragraph::new(Text::from(lines))
         .block(
@@ -337,19 +614,181 @@ fn draw_help_overlay(frame: &mut Frame, area: Rect, _app: &App) {
     frame.render_widget(help, overlay);
 }
 
-fn keybind_line(key: &'static str, desc: &'static str) -> Line<'static> {
+fn draw_detail_popup(frame: &mut Frame, area: Rect, msg: &ChatMessage) {
+    let ts = msg.timestamp;
+    let utc = ts.with_timezone(&chrono::Utc);
+    let age = chrono::Local::now().signed_duration_since(ts);
+    let age_str = if age.num_days() > 0 {
+        format!("{}d {}h ago", age.num_days(), age.num_hours() % 24)
+    } else if age.num_hours() > 0 {
+        format!("{}h {}m ago", age.num_hours(), age.num_minutes() % 60)
+    } else {
+        format!("{}m {}s ago", age.num_minutes(), age.num_seconds() % 60)
+    };
+
+    let lines = vec![
+        detail_line("From", msg.from.clone()),
+        detail_line("Date", ts.format("%A, %d %B %Y").to_string()),
+        detail_line("Time", ts.format("%H:%M:%S").to_string()),
+        detail_line("Zone", format!("{} (UTC{})", local_zone_name(), ts.format("%:z"))),
+        detail_line("UTC", utc.format("%Y-%m-%d %H:%M:%S").to_string()),
+        detail_line("Age", age_str),
+        Line::from(""),
+        Line::from(Span::styled("  [ Any key = close ]", Style::default().fg(Color::DarkGray))),
+    ];
+
+    let height = lines.len() as u16 + 2;
+    let overlay = Rect {
+        x: area.width.saturating_sub(52) / 2,
+        y: area.height.saturating_sub(height) / 2,
+        width: 52.min(area.width),
+        height: height.min(area.height),
+    };
+    frame.render_widget(Clear, overlay);
+
+    let popup = Paragraph::new(Text::from(lines))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(ratatui::widgets::BorderType::Rounded)
+                .border_style(Style::default().fg(Color::DarkGray))
+                .title(Span::styled(
+                    " MESSAGE ",
+                    Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                )),
+        );
+    frame.render_widget(popup, overlay);
+}
+
+fn draw_user_popup(frame: &mut Frame, area: Rect, app: &App, popup: &UserPopup) {
+    let name = popup.name.as_str();
+    let presence = app.chat.presence_of(name);
+    let key = if name == app.chat.username {
+        app.pubkey_b64.as_deref()
+    } else {
+        app.chat.user_keys.get(name).map(String::as_str)
+    };
+    let fingerprint = key
+        .and_then(crate::crypto::fingerprint)
+        .unwrap_or_else(|| "not shared by server".into());
+
+    let mut state = presence.state.as_str().to_string();
+    if let Some(since) = presence.since.filter(|_| presence.state != PresenceState::Online) {
+        state.push_str(&format!(" for {}", short_age(since)));
+    }
+
+    let mut lines = vec![
+        detail_line("User", name.to_string()),
+        detail_line("Key", fingerprint),
+    ];
+    if let Some(symbols) = key.and_then(crate::crypto::fingerprint_symbols) {
+        let emoji: Vec<&str> = symbols.iter().map(|(e, _)| *e).collect();
+        lines.push(detail_line("", emoji.join(" ")));
+    }
+    if name != app.chat.username {
+        if let Some(safety) = app.safety_number(name) {
+            let (first, second) = safety.split_at(safety.len() / 2);
+            lines.push(detail_line("Safety", first.trim().to_string()));
+            lines.push(detail_line("", second.trim().to_string()));
+        }
+        let trust = match app.trust_of(name) {
+            Trust::Verified => "✔ verified",
+            Trust::Changed => "⚠ KEY CHANGED since you verified it",
+            Trust::Unverified => "not verified — compare the safety number",
+            Trust::Unknown => "unknown",
+        };
+        lines.push(detail_line("Trust", trust.into()));
+    }
+    lines.push(detail_line("State", state));
+    if let Some(status) = presence.status {
+        lines.push(detail_line("Status", status));
+    }
+    if app.config.ignored.iter().any(|u| u == name) {
+        lines.push(detail_line("", "(ignored)".into()));
+    }
+    lines.push(Line::from(""));
+    for (i, action) in popup.actions.iter().enumerate() {
+        let style = if i == popup.cursor {
+            Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+        lines.push(Line::from(vec![
+            Span::raw("  "),
+            Span::styled(format!(" {} ", action.label()), style),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "  [ ↑↓ choose · Enter run · Esc close ]",
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let height = lines.len() as u16 + 2;
+    let overlay = Rect {
+        x: area.width.saturating_sub(52) / 2,
+        y: area.height.saturating_sub(height) / 2,
+        width: 52.min(area.width),
+        height: height.min(area.height),
+    };
+    frame.render_widget(Clear, overlay);
+
+    let popup = Paragraph::new(Text::from(lines))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(ratatui::widgets::BorderType::Rounded)
+                .border_style(Style::default().fg(Color::DarkGray))
+                .title(Span::styled(
+                    " USER ",
+                    Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                )),
+        );
+    frame.render_widget(popup, overlay);
+}
+
+fn local_zone_name() -> String {
+    if let Ok(tz) = std::env::var("TZ") {
+        if !tz.is_empty() {
+            return tz.trim_start_matches(':').to_string();
+        }
+    }
+    std::fs::read_link("/etc/localtime")
+        .ok()
+        .and_then(|p| {
+            let p = p.to_string_lossy().into_owned();
+            p.split_once("zoneinfo/").map(|(_, zone)| zone.to_string())
+        })
+        .unwrap_or_else(|| "local".into())
+}
+
+fn detail_line(label: &'static str, value: String) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(format!("  {:8}", label), Style::default().fg(Color::DarkGray)),
+        Span::styled(value, Style::default().fg(Color::White)),
+    ])
+}
+
+fn keybind_line(key: &str, desc: &str) -> Line<'static> {
     Line::from(vec![
         Span::styled(
-            format!("  {:14}", key),
+            format!("  {:16}", key),
             Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
         ),
-        Span::styled(desc, Style::default().fg(Color::Gray)),
+        Span::styled(desc.to_string(), Style::default().fg(Color::Gray)),
     ])
 }
-pub fn handle_key(app: &mut App, key: crossterm::event::KeyEvent) -> bool {
-    use crossterm::event::{KeyCode, KeyModifiers};
+pub fn handle_key(app: &mut App, key: crossterm::event::KeyEvent, actions: &[Action]) -> bool {
+    use crossterm::event::KeyCode;
+
+    if let Some(id) = app.chat.confirm_delete.take() {
+        if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+            app.send_net(crate::net::NetCommand::DeleteMessage { id });
+        }
+        return false;
+    }
 
-    if key.code == KeyCode::F(1) {
+    if actions.contains(&Action::Help) {
         app.chat.show_help = !app.chat.show_help;
         return false;
     }
@@ -359,91 +798,139 @@ pub fn handle_key(app: &mut App, key: crossterm::event::KeyEvent) -> bool {
         return false;
     }
 
-    match key.code {
-        KeyCode::Up => { app.chat.scroll_up(1); return false; }
-        KeyCode::Down => { app.chat.scroll_down(1); return false; }
-        KeyCode::PageUp => { app.chat.scroll_up(10); return false; }
-        KeyCode::PageDown => { app.chat.scroll_down(10); return false; }
-        KeyCode::Home => { app.chat.scroll_to_top(); return false; }
-        KeyCode::End => { app.chat.scroll_to_bottom(); return false; }
-        _ => {}
+    if app.chat.detail.is_some() {
+        app.chat.detail = None;
+        return false;
     }
 
-    match key.code {
-        KeyCode::Enter => {
+    if let Some((idx, cursor)) = app.chat.reaction_picker {
+        let last = QUICK_REACTIONS.len() - 1;
+        match key.code {
+            KeyCode::Left | KeyCode::Char('h') => {
+                app.chat.reaction_picker = Some((idx, cursor.saturating_sub(1)));
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                app.chat.reaction_picker = Some((idx, (cursor + 1).min(last)));
+            }
+            KeyCode::Char(c @ '1'..='8') => {
+                app.chat.reaction_picker = None;
+                app.toggle_reaction(idx, QUICK_REACTIONS[c as usize - '1' as usize]);
+            }
+            KeyCode::Enter => {
+                app.chat.reaction_picker = None;
+                app.toggle_reaction(idx, QUICK_REACTIONS[cursor]);
+            }
+            _ => app.chat.reaction_picker = None,
+        }
+        return false;
+    }
+
+    if app.chat.user_popup.is_some() {
+        handle_user_popup_key(app, key);
+        return false;
+    }
+
+    if app.chat.selected.is_some() {
+        handle_selection_key(app, key);
+        return false;
+    }
+
+    if app.chat.focus_users {
+        handle_user_list_key(app, key);
+        return false;
+    }
+
+    if key.code == KeyCode::Esc && app.chat.editing.is_some() {
+        app.chat.editing = None;
+        app.chat.input.clear();
+        app.chat.input_cursor = 0;
+        return false;
+    }
+
+    if key.code == KeyCode::Esc && app.chat.replying_to.is_some() {
+        app.chat.replying_to = None;
+        return false;
+    }
+
+    let Some(&action) = actions.first() else {
+        return handle_input_key(app, key);
+    };
+    match action {
+        Action::SelectMode => {
+            if let Some(last) = app.chat.messages.len().checked_sub(1) {
+                app.chat.selected = Some(last);
+                ensure_selected_visible(app);
+            }
+        }
+        Action::EditLast => {
+            if let Some(idx) = app.chat.last_own_editable() {
+                app.start_edit(idx);
+            }
+        }
+        Action::ScrollUp => app.chat.scroll_up(1),
+        Action::ScrollDown => app.chat.scroll_down(1),
+        Action::PageUp => app.chat.scroll_up(10),
+        Action::PageDown => app.chat.scroll_down(10),
+        Action::ScrollTop => app.chat.scroll_to_top(),
+        Action::ScrollBottom => app.chat.scroll_to_bottom(),
+        Action::JumpUnread => jump_to_marker(app),
+        Action::MessageDetails => {
+            app.chat.detail = app.chat.messages.iter().rposition(|m| !m.is_system);
+        }
+        Action::ClearInput => {
+            app.chat.input.clear();
+            app.chat.input_cursor = 0;
+            app.on_input_changed();
+        }
+        Action::Send => {
             let text = app.chat.input.trim().to_string();
             if !text.is_empty() {
-                    if let Some(tx) = &app.net_cmd_tx {
-                        if text == "/mute" {
-                            app.notifications_muted = true;
-                            app.chat.input.clear();
-                            app.chat.input_cursor = 0;
-                            app.chat.messages.push(crate::app::ChatMessage {
-                                from: "─ sys ─".into(),
-                                text: "Notifications muted. Type /unmute to re-enable.".into(),
-                                timestamp: chrono::Local::now().format("%H:%M").to_string(),
-                                is_system: true,
-                                is_admin: false,
-                            });
-                            return false;
-                        } else if text == "/unmute" {
-                            app.notifications_muted = false;
-                            app.chat.input.clear();
-                            app.chat.input_cursor = 0;
-                            app.chat.messages.push(crate::app::ChatMessage {
-                                from: "─ sys ─".into(),
-                                text: "Notifications unmuted.".into(),
-                                timestamp: chrono::Local::now().format("%H:%M").to_string(),
-                                is_system: true,
-                                is_admin: false,
-                            });
-                            return false;
-                        } else if let Some(cmd) = text.strip_prefix("/admin ") {
-                            let action = cmd.trim().to_string();
-                            if !action.is_empty() {
-                                let _ = tx.send(crate::net::NetCommand::SendAdminCmd(action));
-                            }
-                        } else if !text.starts_with('/') || text.starts_with("/admin ") {
-                            let _ = tx.send(crate::net::NetCommand::SendMessage(text));
-                        } else {
-                            let _ = tx.send(crate::net::NetCommand::SendMessage(text));
-                        }
-                    }
                 app.chat.input.clear();
                 app.chat.input_cursor = 0;
+                app.on_input_changed();
+                if let Some(id) = app.chat.editing.take() {
+                    app.send_net(crate::net::NetCommand::EditMessage { id, text });
+                    return false;
+                }
+                if app.handle_command(&text) {
+                    return false;
+                }
+                let reply_to = app.chat.replying_to.take();
+                app.send_net(crate::net::NetCommand::SendMessage { text, reply_to });
                 app.chat.scroll_to_bottom();
             }
         }
-        KeyCode::Backspace => {
-            if app.chat.input_cursor > 0 {
-                let byte_pos = app.chat.input.char_indices()
-                    .nth(app.chat.input_cursor - 1)
-                    .map(|(i, _)| i)
-                    .unwrap_or(0);
-                app.chat.input.remove(byte_pos);
-                app.chat.input_cursor -= 1;
-            }
-        }
-        KeyCode::Delete => {
-            if app.chat.input_cursor < app.chat.input.chars().count() {
-                let byte_pos = app.chat.input.char_indices()
-                    .nth(app.chat.input_cursor)
-                    .map(|(i, _)| i)
-                    .unwrap_or(0);
-                app.chat.input.remove(byte_pos);
-            }
+        _ => return handle_input_key(app, key),
+    }
+    false
+}
+
+fn handle_input_key(app: &mut App, key: crossterm::event::KeyEvent) -> bool {
+    use crossterm::event::KeyCode;
+
+    let input_before = app.chat.input.len();
+
+    match key.code {
+        KeyCode::Backspace if app.chat.input_cursor > 0 => {
+            let byte_pos = app.chat.input.char_indices()
+                .nth(app.chat.input_cursor - 1)
+                .map(|(i, _)| i)
+                .unwrap_or(0);
+            app.chat.input.remove(byte_pos);
+            app.chat.input_cursor -= 1;
         }
-        KeyCode::Left => {
-            if app.chat.input_cursor > 0 { app.chat.input_cursor -= 1; }
+        KeyCode::Delete if app.chat.input_cursor < app.chat.input.chars().count() => {
+            let byte_pos = app.chat.input.char_indices()
+                .nth(app.chat.input_cursor)
+                .map(|(i, _)| i)
+                .unwrap_or(0);
+            app.chat.input.remove(byte_pos);
         }
-        KeyCode::Right => {
-            if app.chat.input_cursor < app.chat.input.chars().count() {
-                app.chat.input_cursor += 1;
-            }
+        KeyCode::Left if app.chat.input_cursor > 0 => {
+            app.chat.input_cursor -= 1;
         }
-        KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-            app.chat.input.clear();
-            app.chat.input_cursor = 0;
+        KeyCode::Right if app.chat.input_cursor < app.chat.input.chars().count() => {
+            app.chat.input_cursor += 1;
         }
         KeyCode::Char(c) => {
             let byte_pos = app.chat.input.char_indices()
@@ -455,5 +942,240 @@ pub fn handle_key(app: &mut App, key: crossterm::event::KeyEvent) -> bool {
         }
         _ => {}
     }
+    if app.chat.input.len() != input_before {
+        app.on_input_changed();
+    }
     false
 }
+
+fn jump_to_marker(app: &mut App) {
+    let Some(marker) = app.chat.unread_marker else { return };
+    let (width, _) = app.chat.msg_view.get();
+    let layout = layout_messages(&app.chat, &app.config.timestamp, width);
+    let total_rows = layout.lines.len();
+    if let Some(&row) = layout.starts.get(marker) {
+        app.chat.scroll_to_row(row.saturating_sub(1), total_rows);
+    }
+}
+
+fn handle_selection_key(app: &mut App, key: crossterm::event::KeyEvent) {
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    let Some(idx) = app.chat.selected else { return };
+    let last = app.chat.messages.len().saturating_sub(1);
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.chat.selected = None;
+            return;
+        }
+        KeyCode::Char('k') if ctrl => {
+            app.chat.selected = None;
+            return;
+        }
+        KeyCode::Up | KeyCode::Char('k') => app.chat.selected = Some(idx.saturating_sub(1)),
+        KeyCode::Down | KeyCode::Char('j') => app.chat.selected = Some((idx + 1).min(last)),
+        KeyCode::Home | KeyCode::Char('g') => app.chat.selected = Some(0),
+        KeyCode::End | KeyCode::Char('G') => app.chat.selected = Some(last),
+        KeyCode::Char('y') => {
+            if let Some(msg) = app.chat.messages.get(idx) {
+                let text = msg.visible_text().to_string();
+                copy_to_clipboard(app, &text);
+            }
+        }
+        KeyCode::Char('Y') => {
+            if let Some(msg) = app.chat.messages.get(idx) {
+                let text = quote_message(msg);
+                copy_to_clipboard(app, &text);
+            }
+        }
+        KeyCode::Char('r') => {
+            if let Some(msg) = app.chat.messages.get(idx) {
+                if let Some(id) = &msg.id {
+                    app.chat.replying_to = Some(id.clone());
+                } else {
+                    let snippet = snippet(msg.visible_text(), 40);
+                    app.chat.input = format!("> {}: {} ", msg.from, snippet);
+                    app.chat.input_cursor = app.chat.input.chars().count();
+                }
+                app.chat.selected = None;
+                app.chat.scroll_to_bottom();
+                return;
+            }
+        }
+        KeyCode::Enter => {
+            let parent = app.chat.messages.get(idx).and_then(|m| m.reply_to.clone());
+            match parent {
+                Some(id) => match app.chat.find_by_id(&id) {
+                    Some(p) => app.chat.selected = Some(p),
+                    None => {
+                        app.chat.flash = Some(("Original message is not loaded".into(), app.tick_count + 35));
+                    }
+                },
+                None => app.chat.detail = Some(idx),
+            }
+        }
+        KeyCode::Char('i') => app.chat.detail = Some(idx),
+        KeyCode::Char('+') => app.chat.reaction_picker = Some((idx, 0)),
+        KeyCode::Char('e') | KeyCode::Char('d') => {
+            let own = app.chat.messages.get(idx)
+                .filter(|m| m.from == app.chat.username && !m.deleted)
+                .and_then(|m| m.id.clone());
+            match own {
+                Some(id) if key.code == KeyCode::Char('d') => {
+                    app.chat.confirm_delete = Some(id);
+                }
+                Some(_) => {
+                    app.start_edit(idx);
+                    app.chat.selected = None;
+                    app.chat.scroll_to_bottom();
+                    return;
+                }
+                None => {
+                    app.chat.flash = Some(("You can only change your own messages".into(), app.tick_count + 35));
+                }
+            }
+        }
+        _ => {}
+    }
+    ensure_selected_visible(app);
+}
+
+fn handle_user_list_key(app: &mut App, key: crossterm::event::KeyEvent) {
+    use crossterm::event::KeyCode;
+
+    let count = app.chat.online_users.len();
+    if count == 0 {
+        app.chat.focus_users = key.code != KeyCode::Esc;
+        return;
+    }
+    let cursor = app.chat.user_cursor.min(count - 1);
+    match key.code {
+        KeyCode::Up | KeyCode::Char('k') => app.chat.user_cursor = cursor.saturating_sub(1),
+        KeyCode::Down | KeyCode::Char('j') => app.chat.user_cursor = (cursor + 1).min(count - 1),
+        KeyCode::Home | KeyCode::Char('g') => app.chat.user_cursor = 0,
+        KeyCode::End | KeyCode::Char('G') => app.chat.user_cursor = count - 1,
+        KeyCode::Enter => {
+            if let Some(name) = app.chat.cursor_user().map(str::to_string) {
+                open_user_popup(app, name);
+            }
+        }
+        KeyCode::Char('@') => {
+            if let Some(name) = app.chat.cursor_user().map(str::to_string) {
+                mention_user(app, &name);
+            }
+        }
+        KeyCode::Esc => app.chat.focus_users = false,
+        _ => {}
+    }
+}
+
+fn open_user_popup(app: &mut App, name: String) {
+    let mut actions = Vec::new();
+    if name != app.chat.username {
+        actions.push(UserAction::Message);
+    }
+    actions.push(UserAction::Mention);
+    if name != app.chat.username {
+        actions.push(UserAction::Ignore);
+        if app.trust_of(&name) != Trust::Unknown {
+            actions.push(UserAction::Verify);
+        }
+        if app.is_admin {
+            actions.extend([UserAction::Kick, UserAction::Ban]);
+        }
+    }
+    app.chat.user_popup = Some(UserPopup { name, actions, cursor: 0 });
+}
+
+fn handle_user_popup_key(app: &mut App, key: crossterm::event::KeyEvent) {
+    use crossterm::event::KeyCode;
+
+    let Some(popup) = app.chat.user_popup.as_mut() else { return };
+    let last = popup.actions.len().saturating_sub(1);
+    match key.code {
+        KeyCode::Up | KeyCode::Char('k') => popup.cursor = popup.cursor.saturating_sub(1),
+        KeyCode::Down | KeyCode::Char('j') => popup.cursor = (popup.cursor + 1).min(last),
+        KeyCode::Enter => {
+            let Some(popup) = app.chat.user_popup.take() else { return };
+            if let Some(action) = popup.actions.get(popup.cursor) {
+                run_user_action(app, *action, &popup.name);
+            }
+        }
+        _ => app.chat.user_popup = None,
+    }
+}
+
+fn run_user_action(app: &mut App, action: UserAction, name: &str) {
+    match action {
+        UserAction::Message => {
+            app.chat.focus_users = false;
+            app.chat.input = format!("/dm {name} ");
+            app.chat.input_cursor = app.chat.input.chars().count();
+        }
+        UserAction::Mention => mention_user(app, name),
+        UserAction::Ignore => app.toggle_ignore(name),
+        UserAction::Verify => app.toggle_verified(name),
+        UserAction::Kick | UserAction::Ban => {
+            let cmd = if action == UserAction::Kick { "user kick" } else { "user ban" };
+            app.open_admin(Some(cmd), &[name]);
+            app.submit_admin();
+        }
+    }
+}
+
+fn mention_user(app: &mut App, name: &str) {
+    app.chat.focus_users = false;
+    let chat = &mut app.chat;
+    let byte_pos = chat.input.char_indices()
+        .nth(chat.input_cursor)
+        .map(|(i, _)| i)
+        .unwrap_or(chat.input.len());
+    let needs_space = byte_pos > 0 && !chat.input[..byte_pos].ends_with(' ');
+    let mention = format!("{}@{name} ", if needs_space { " " } else { "" });
+    chat.input.insert_str(byte_pos, &mention);
+    chat.input_cursor += mention.chars().count();
+}
+
+fn ensure_selected_visible(app: &mut App) {
+    let Some(idx) = app.chat.selected else { return };
+    let (width, height) = app.chat.msg_view.get();
+    let layout = layout_messages(&app.chat, &app.config.timestamp, width);
+    let total_rows = layout.lines.len();
+    let Some(&start) = layout.starts.get(idx) else { return };
+    let end = layout.starts.get(idx + 1).copied().unwrap_or(total_rows);
+
+    let max_scroll = total_rows.saturating_sub(height);
+    let top = max_scroll.saturating_sub(app.chat.scroll_offset.min(max_scroll));
+    if start < top {
+        app.chat.scroll_to_row(start, total_rows);
+    } else if end > top + height {
+        app.chat.scroll_to_row(end.saturating_sub(height), total_rows);
+    }
+}
+
+fn copy_to_clipboard(app: &mut App, text: &str) {
+    let msg = if crate::clipboard::copy_osc52(text) {
+        "Copied to clipboard"
+    } else {
+        "Clipboard unavailable"
+    };
+    app.chat.flash = Some((msg.into(), app.tick_count + 35));
+}
+
+fn quote_message(msg: &ChatMessage) -> String {
+    msg.visible_text()
+        .lines()
+        .map(|line| format!("> {}: {}", msg.from, line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn snippet(text: &str, max: usize) -> String {
+    let mut s: String = text.chars().take(max).collect();
+    if text.chars().count() > max {
+        s.push('…');
+    }
+    s
+}
@@ -7,6 +7,7 @@ use ratatui::{
 };
 
 use crate::app::App;
+use crate::keymap::Action;
 use crate::ui::centered_rect;
 
 pub fn draw_connect(frame: &mut Frame, app: &App) {
@@ -21,13 +22,17 @@ pub fn draw_connect(frame: &mut Frame, app: &App) {
 
     draw_sidebar(frame, app, layout[0]);
     draw_join_form(frame, app, layout[1]);
+
+    if let Some(editor) = &app.profile_editor {
+        crate::ui::profiles::draw_editor(frame, app, editor);
+    }
 }
 
 fn draw_sidebar(frame: &mut Frame, app: &App, area: Rect) {
     let block = Block::default()
         .borders(Borders::RIGHT)
         .border_style(Style::default().fg(Color::DarkGray))
-        .title(Span::styled(" RECENT ", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)));
+        .title(Span::styled(" PROFILES ", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)));
     
     let inner = block.inner(area);
     frame.render_widget(block, area);
@@ -48,12 +53,39 @@ fn draw_sidebar(frame: &mut Frame, app: &App, area: Rect) {
                 ("   ", Style::default().fg(Color::Gray))
             };
 
-            lines.push(Line::from(vec![
-                Span::styled(prefix, style),
-                Span::styled(&p.username, style),
-                Span::styled("@", Style::default().fg(Color::DarkGray)),
-                Span::styled(&p.server, style),
-            ]));
+            let mut spans = vec![Span::styled(prefix, style)];
+            if p.pinned {
+                spans.push(Span::styled("★ ", Style::default().fg(Color::Yellow)));
+            }
+            if p.name.is_empty() {
+                spans.push(Span::styled(&p.username, style));
+                spans.push(Span::styled("@", Style::default().fg(Color::DarkGray)));
+                spans.push(Span::styled(&p.server, style));
+            } else {
+                spans.push(Span::styled(&p.name, style));
+            }
+            if p.auto_connect {
+                spans.push(Span::styled(" ⚡", Style::default().fg(Color::Yellow)));
+            }
+            lines.push(Line::from(spans));
+            if is_focused && !p.notes.is_empty() {
+                lines.push(Line::from(Span::styled(
+                    format!("     {}", p.notes),
+                    Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+                )));
+            }
+        }
+    }
+
+    if app.focus_on_profiles {
+        lines.push(Line::from(""));
+        let hint = match app.profile_delete_armed {
+            Some(i) => format!(" Press d again to delete {}", app.profiles.get(i).map(|p| p.label()).unwrap_or_default()),
+            None => " Enter connect · e edit · n new\n d delete · p pin · a auto · J/K move".into(),
+        };
+        for l in hint.lines() {
+            let color = if app.profile_delete_armed.is_some() { Color::Red } else { Color::DarkGray };
+            lines.push(Line::from(Span::styled(l.to_string(), Style::default().fg(color))));
         }
     }
 
@@ -96,8 +128,10 @@ fn draw_join_form(frame: &mut Frame, app: &App, area: Rect) {
 
     let render_field = |frame: &mut Frame, area: Rect, label: &str, val: &str, idx: usize, is_pass: bool| {
         let is_active = form_focused && focused == idx;
-        let display_val = if is_pass && !val.is_empty() {
-            "*".repeat(val.len())
+        let display_val = if is_pass && crate::crypto::is_backup(val) {
+            "[encrypted key backup]".to_string()
+        } else if is_pass && !val.is_empty() {
+            format!("•••••••• ({} chars)", val.chars().count())
         } else {
             val.to_string()
         };
@@ -144,38 +178,110 @@ fn draw_join_form(frame: &mut Frame, app: &App, area: Rect) {
 
     frame.render_widget(Paragraph::new(Span::styled("  [ Wipe Local Identity ]", Style::default().fg(if is_reset_active { Color::Red } else { Color::DarkGray }))).block(reset_block), verts[5]);
 
+    if let Some(err) = &app.config_error {
+        let warning = Paragraph::new(Span::styled(
+            format!("Settings error: {err}"),
+            Style::default().fg(Color::Red),
+        ))
+        .wrap(ratatui::widgets::Wrap { trim: true })
+        .alignment(Alignment::Center);
+        frame.render_widget(warning, verts[6]);
+    } else if app.connect_form.invite_code.is_some() {
+        let invite = Paragraph::new(Span::styled(
+            "Invite loaded: Enter enrolls this username on the server",
+            Style::default().fg(Color::LightGreen),
+        ))
+        .alignment(Alignment::Center);
+        frame.render_widget(invite, verts[6]);
+    }
+
     let hints = Line::from(vec![
-        Span::styled(if app.focus_on_profiles { " ←/→ Switch to FORM " } else { " ←/→ Switch to RECENT " }, Style::default().fg(Color::DarkGray)),
+        Span::styled(if app.focus_on_profiles { " ←/→ Switch to FORM " } else { " ←/→ Switch to PROFILES " }, Style::default().fg(Color::DarkGray)),
         Span::styled("  │  ", Style::default().fg(Color::Rgb(30, 30, 30))),
         Span::styled(" Tab = Move ", Style::default().fg(Color::DarkGray)),
     ]);
     frame.render_widget(Paragraph::new(hints).alignment(Alignment::Center), verts[7]);
+
+    if let Some(pass) = &app.connect_form.backup_passphrase {
+        draw_backup_prompt(frame, app, pass);
+    }
 }
 
-pub fn handle_key(app: &mut App, key: crossterm::event::KeyEvent) {
+fn draw_backup_prompt(frame: &mut Frame, app: &App, pass: &str) {
+    let area = centered_rect(frame.area(), 52, 8);
+    frame.render_widget(ratatui::widgets::Clear, area);
+    let cursor = if (app.tick_count / 6).wrapping_rem(2) == 0 { "█" } else { " " };
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("  Passphrase: ", Style::default().fg(Color::Gray)),
+            Span::styled(format!("{}{cursor}", "•".repeat(pass.chars().count().min(30))), Style::default().fg(Color::White)),
+        ]),
+        Line::from(""),
+    ];
+    match &app.connect_form.backup_error {
+        Some(err) => lines.push(Line::from(Span::styled(format!("  {err}"), Style::default().fg(Color::Red)))),
+        None => lines.push(Line::from("")),
+    }
+    lines.push(Line::from(Span::styled("  [ Enter = unlock  |  Esc = cancel ]", Style::default().fg(Color::DarkGray))));
+    let popup = Paragraph::new(Text::from(lines))
+        .wrap(ratatui::widgets::Wrap { trim: false })
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(Color::Cyan))
+                .title(Span::styled(" UNLOCK KEY BACKUP ", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))),
+        );
+    frame.render_widget(popup, area);
+}
+
+pub fn handle_key(app: &mut App, key: crossterm::event::KeyEvent, actions: &[Action]) {
     use crossterm::event::KeyCode;
-    match key.code {
-        KeyCode::Tab => {
-            app.connect_form.focused_field = (app.connect_form.focused_field + 1) % 5;
-        }
-        KeyCode::BackTab => {
-            app.connect_form.focused_field = (app.connect_form.focused_field + 4) % 5;
+    if let Some(pass) = app.connect_form.backup_passphrase.as_mut() {
+        match key.code {
+            KeyCode::Enter => app.start_connection(app.connect_form.invite_code.clone()),
+            KeyCode::Esc => {
+                use zeroize::Zeroize;
+                pass.zeroize();
+                app.connect_form.backup_passphrase = None;
+                app.connect_form.backup_error = None;
+            }
+            KeyCode::Backspace => { pass.pop(); }
+            KeyCode::Char(c) => pass.push(c),
+            _ => {}
         }
+        return;
+    }
+    if actions.contains(&Action::NextField) {
+        app.connect_form.focused_field = (app.connect_form.focused_field + 1) % 5;
+        return;
+    }
+    if actions.contains(&Action::PrevField) {
+        app.connect_form.focused_field = (app.connect_form.focused_field + 4) % 5;
+        return;
+    }
+    match key.code {
         KeyCode::Enter => {
             if app.connect_form.focused_field == 4 {
                 app.reset_identity();
                 return;
             }
+            if let Some(invite) = crate::invite::Invite::parse(&app.connect_form.server) {
+                app.apply_invite(invite);
+                return;
+            }
             if !app.connect_form.server.trim().is_empty()
                 && !app.connect_form.username.trim().is_empty()
             {
-                app.start_connection(None);
+                app.start_connection(app.connect_form.invite_code.clone());
             }
         }
         KeyCode::Char(' ') if app.connect_form.focused_field == 3 => {
             app.connect_form.insecure = !app.connect_form.insecure;
         }
         KeyCode::Backspace => {
+            app.connect_form.key_file = None;
             match app.connect_form.focused_field {
                 0 => { app.connect_form.server.pop(); }
                 1 => { app.connect_form.username.pop(); }
@@ -184,6 +290,7 @@ pub fn handle_key(app: &mut App, key: crossterm::event::KeyEvent) {
             }
         }
         KeyCode::Char(c) => {
+            app.connect_form.key_file = None;
             match app.connect_form.focused_field {
                 0 => app.connect_form.server.push(c),
                 1 => app.connect_form.username.push(c),
@@ -1,5 +1,6 @@
 pub mod chat;
 pub mod connect;
+pub mod profiles;
 pub mod screens;
 pub mod splash;
 pub mod assets;
@@ -7,6 +8,7 @@ pub mod assets;
 use ratatui::Frame;
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use crate::app::{App, Screen};
+use crate::config::Theme;
 
 pub fn draw(frame: &mut Frame, app: &App) {
     match &app.screen {
@@ -16,8 +18,53 @@ pub fn draw(frame: &mut Frame, app: &App) {
         Screen::Auth       => screens::draw_auth(frame, app),
         Screen::Enroll     => screens::draw_enroll(frame, app),
         Screen::Chat       => chat::draw_chat(frame, app),
+        Screen::Filters    => screens::draw_filters(frame, app),
+        Screen::Admin      => screens::draw_admin(frame, app),
         Screen::Error(msg) => screens::draw_error(frame, app, msg),
     }
+
+    if app.confirm_quit {
+        draw_quit_confirm(frame);
+    }
+
+    if app.config.theme() == Theme::Mono {
+        apply_mono(frame);
+    }
+}
+
+fn draw_quit_confirm(frame: &mut Frame) {
+    use ratatui::style::{Color, Modifier, Style};
+    use ratatui::text::{Line, Span, Text};
+    use ratatui::widgets::{Block, BorderType, Borders, Clear, Paragraph};
+
+    let area = centered_rect(frame.area(), 46, 6);
+    frame.render_widget(Clear, area);
+    let lines = vec![
+        Line::from(""),
+        Line::from(Span::styled("  Quit ttychat?", Style::default().fg(Color::White).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from(Span::styled("  [ y / Enter = quit  |  any key = stay ]", Style::default().fg(Color::DarkGray))),
+    ];
+    let popup = Paragraph::new(Text::from(lines)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Red))
+            .title(Span::styled(" QUIT ", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))),
+    );
+    frame.render_widget(popup, area);
+}
+
+/// Strips colours from the rendered frame, keeping highlights as reverse video.
+fn apply_mono(frame: &mut Frame) {
+    use ratatui::style::{Color, Modifier};
+    for cell in frame.buffer_mut().content.iter_mut() {
+        if cell.bg != Color::Reset {
+            cell.modifier.insert(Modifier::REVERSED);
+        }
+        cell.fg = Color::Reset;
+        cell.bg = Color::Reset;
+    }
 }
 
 pub fn centered_rect(area: Rect, w: u16, h: u16) -> Rect {
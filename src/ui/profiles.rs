@@ -0,0 +1,139 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, BorderType, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::app::{App, ProfileEditor, PROFILE_TEXT_FIELDS};
+use crate::config::ServerProfile;
+use crate::ui::centered_rect;
+
+const TLS_FIELD: usize = PROFILE_TEXT_FIELDS.len();
+const AUTO_FIELD: usize = TLS_FIELD + 1;
+
+pub fn draw_editor(frame: &mut Frame, app: &App, editor: &ProfileEditor) {
+    let area = centered_rect(frame.area(), 60, 16);
+    frame.render_widget(Clear, area);
+
+    let cursor_visible = (app.tick_count / 6).wrapping_rem(2) == 0;
+    let label_style = |active: bool| {
+        Style::default().fg(if active { Color::Cyan } else { Color::DarkGray })
+    };
+
+    let mut lines = vec![Line::from("")];
+    for (i, (label, value)) in PROFILE_TEXT_FIELDS.iter().zip(&editor.fields).enumerate() {
+        let active = editor.focused == i;
+        let caret = if active && cursor_visible { "█" } else { "" };
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {label:10}"), label_style(active)),
+            Span::styled(
+                format!("{value}{caret}"),
+                Style::default().fg(if active { Color::White } else { Color::Gray }),
+            ),
+        ]));
+    }
+
+    let checkbox = |on: bool| if on { "[✔]" } else { "[ ]" };
+    lines.push(Line::from(vec![
+        Span::styled(format!("  {:10}", "TLS"), label_style(editor.focused == TLS_FIELD)),
+        Span::styled(
+            format!("{} Skip cert verification", checkbox(editor.insecure)),
+            Style::default().fg(if editor.insecure { Color::Yellow } else { Color::Gray }),
+        ),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled(format!("  {:10}", "STARTUP"), label_style(editor.focused == AUTO_FIELD)),
+        Span::styled(
+            format!("{} Connect automatically on launch", checkbox(editor.auto_connect)),
+            Style::default().fg(Color::Gray),
+        ),
+    ]));
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!("  {}", editor.error.as_deref().unwrap_or("")),
+        Style::default().fg(Color::Red),
+    )));
+    lines.push(Line::from(Span::styled(
+        "  [ Tab = move  |  Space = toggle  |  Enter = save  |  Esc = cancel ]",
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let title = if editor.index.is_some() { " EDIT PROFILE " } else { " NEW PROFILE " };
+    let para = Paragraph::new(Text::from(lines)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Cyan))
+            .title(Span::styled(title, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))),
+    );
+    frame.render_widget(para, area);
+}
+
+pub fn handle_editor_key(app: &mut App, key: crossterm::event::KeyEvent) {
+    use crossterm::event::KeyCode;
+
+    if key.code == KeyCode::Enter {
+        app.save_profile_editor();
+        return;
+    }
+    let Some(editor) = app.profile_editor.as_mut() else { return };
+    let field_count = AUTO_FIELD + 1;
+    match key.code {
+        KeyCode::Esc => app.profile_editor = None,
+        KeyCode::Tab | KeyCode::Down => editor.focused = (editor.focused + 1) % field_count,
+        KeyCode::BackTab | KeyCode::Up => {
+            editor.focused = (editor.focused + field_count - 1) % field_count;
+        }
+        KeyCode::Char(' ') if editor.focused == TLS_FIELD => editor.insecure = !editor.insecure,
+        KeyCode::Char(' ') if editor.focused == AUTO_FIELD => {
+            editor.auto_connect = !editor.auto_connect;
+        }
+        KeyCode::Backspace if editor.focused < TLS_FIELD => {
+            editor.fields[editor.focused].pop();
+        }
+        KeyCode::Char(c) if editor.focused < TLS_FIELD => editor.fields[editor.focused].push(c),
+        _ => {}
+    }
+}
+
+pub fn handle_sidebar_key(app: &mut App, key: crossterm::event::KeyEvent) {
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    let count = app.profiles.len();
+    let selected = app.selected_profile.unwrap_or(0).min(count.saturating_sub(1));
+    let armed = app.profile_delete_armed.take();
+    let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+
+    match key.code {
+        KeyCode::Up | KeyCode::Char('K') if shift && count > 0 => app.move_profile(selected, true),
+        KeyCode::Down | KeyCode::Char('J') if shift && count > 0 => app.move_profile(selected, false),
+        KeyCode::Up | KeyCode::Char('k') if count > 0 => {
+            app.selected_profile = Some(selected.saturating_sub(1));
+        }
+        KeyCode::Down | KeyCode::Char('j') if count > 0 => {
+            app.selected_profile = Some((selected + 1).min(count - 1));
+        }
+        KeyCode::Enter if count > 0 => app.connect_profile(selected),
+        KeyCode::Char('e') if count > 0 => {
+            app.profile_editor = Some(ProfileEditor::new_from(&app.profiles[selected], Some(selected)));
+        }
+        KeyCode::Char('n') => {
+            let form = &app.connect_form;
+            let mut draft = ServerProfile::new(form.server.trim(), form.username.trim());
+            draft.insecure = form.insecure;
+            app.profile_editor = Some(ProfileEditor::new_from(&draft, None));
+        }
+        KeyCode::Char('d') | KeyCode::Delete if count > 0 => {
+            if armed == Some(selected) {
+                app.delete_profile(selected);
+            } else {
+                app.profile_delete_armed = Some(selected);
+            }
+        }
+        KeyCode::Char('p') if count > 0 => app.toggle_pinned(selected),
+        KeyCode::Char('a') if count > 0 => app.toggle_auto_connect(selected),
+        _ => {}
+    }
+}
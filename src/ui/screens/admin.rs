@@ -0,0 +1,271 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, Wrap},
+    Frame,
+};
+use serde_json::Value;
+
+use crate::app::admin::ADMIN_COMMANDS;
+use crate::app::{App, Screen};
+use crate::ui::centered_rect;
+
+pub fn draw_admin(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Fill(1), Constraint::Length(1)])
+        .split(area);
+    let cols = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(34), Constraint::Fill(1)])
+        .split(rows[1]);
+
+    let role = if app.is_admin { "" } else { " · server did not report admin role" };
+    let title = Line::from(vec![
+        Span::styled(" ADMIN CONSOLE ", Style::default().fg(Color::Black).bg(Color::Red).add_modifier(Modifier::BOLD)),
+        Span::styled(format!(" {}{role}", app.chat.server), Style::default().fg(Color::DarkGray)),
+    ]);
+    frame.render_widget(Paragraph::new(title), rows[0]);
+
+    draw_command_panel(frame, app, cols[0]);
+    draw_result_panel(frame, app, cols[1]);
+
+    let hints = if app.admin.field.is_some() {
+        " Tab/↑↓ field · Enter run · Esc back to commands"
+    } else {
+        " ↑↓ command · Enter run/fill · [ ] older/newer result · Esc chat"
+    };
+    frame.render_widget(
+        Paragraph::new(Span::styled(hints, Style::default().fg(Color::DarkGray))),
+        rows[2],
+    );
+
+    if app.admin.confirm {
+        draw_confirm(frame, area, &app.admin.preview());
+    }
+}
+
+fn draw_command_panel(frame: &mut Frame, app: &App, area: Rect) {
+    let console = &app.admin;
+    let mut lines = Vec::new();
+    for (i, cmd) in ADMIN_COMMANDS.iter().enumerate() {
+        let selected = i == console.cursor;
+        let style = if selected && console.field.is_none() {
+            Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD)
+        } else if selected {
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)
+        } else if cmd.destructive {
+            Style::default().fg(Color::LightRed)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+        lines.push(Line::from(Span::styled(format!(" {:30}", cmd.name), style)));
+    }
+
+    lines.push(Line::from(""));
+    let cmd = console.command();
+    if cmd.args.is_empty() {
+        lines.push(Line::from(Span::styled(" (no arguments)", Style::default().fg(Color::DarkGray))));
+    }
+    let cursor_visible = (app.tick_count / 6).wrapping_rem(2) == 0;
+    for (i, (arg, value)) in cmd.args.iter().zip(&console.fields).enumerate() {
+        let focused = console.field == Some(i);
+        let label = if arg.required { format!(" {}*", arg.name) } else { format!(" {}", arg.name) };
+        let caret = if focused && cursor_visible { "█" } else { "" };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{label:13}"), Style::default().fg(Color::DarkGray)),
+            Span::styled(
+                format!("{value}{caret}"),
+                if focused {
+                    Style::default().fg(Color::LightCyan).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::White)
+                },
+            ),
+        ]));
+    }
+    if let Some(err) = &console.error {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(format!(" {err}"), Style::default().fg(Color::Red))));
+    }
+    if !console.pending.is_empty() {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            format!(" {} request(s) pending…", console.pending.len()),
+            Style::default().fg(Color::Yellow),
+        )));
+    }
+
+    let panel = Paragraph::new(Text::from(lines)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(ratatui::widgets::BorderType::Rounded)
+            .border_style(Style::default().fg(Color::DarkGray))
+            .title(Span::styled(" COMMANDS ", Style::default().fg(Color::Cyan))),
+    );
+    frame.render_widget(panel, area);
+}
+
+fn draw_result_panel(frame: &mut Frame, app: &App, area: Rect) {
+    let results = &app.admin.results;
+    let shown = results.len().checked_sub(app.admin.viewing + 1).and_then(|i| results.get(i));
+
+    let title = match shown {
+        Some(r) => format!(
+            " {}{} · {} · {}/{} ",
+            r.action,
+            r.req_id.map(|id| format!(" #{id}")).unwrap_or_default(),
+            r.received.format("%H:%M:%S"),
+            results.len() - app.admin.viewing,
+            results.len(),
+        ),
+        None => " RESULTS ".to_string(),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(ratatui::widgets::BorderType::Rounded)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(Span::styled(title, Style::default().fg(Color::Cyan)));
+
+    let Some(result) = shown else {
+        let empty = Paragraph::new(Span::styled(" No responses yet.", Style::default().fg(Color::DarkGray)))
+            .block(block);
+        frame.render_widget(empty, area);
+        return;
+    };
+
+    match data_table(&result.data) {
+        Some((headers, rows)) => frame.render_widget(render_table(headers, rows).block(block), area),
+        None => {
+            let text = match &result.data {
+                Value::String(s) => s.clone(),
+                other => serde_json::to_string_pretty(other).unwrap_or_default(),
+            };
+            let para = Paragraph::new(text)
+                .style(Style::default().fg(Color::White))
+                .wrap(Wrap { trim: false })
+                .block(block);
+            frame.render_widget(para, area);
+        }
+    }
+}
+
+fn render_table(headers: Vec<String>, rows: Vec<Vec<String>>) -> Table<'static> {
+    let widths: Vec<Constraint> = headers.iter()
+        .enumerate()
+        .map(|(i, h)| {
+            let widest = rows.iter().map(|r| r[i].chars().count()).max().unwrap_or(0);
+            Constraint::Length(widest.max(h.len()).min(40) as u16)
+        })
+        .collect();
+    let header = Row::new(headers.into_iter().map(|h| Cell::from(h.to_uppercase())))
+        .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+    let body: Vec<Row> = rows.into_iter().map(|r| Row::new(r.into_iter().map(Cell::from))).collect();
+    Table::new(body, widths)
+        .header(header)
+        .column_spacing(2)
+        .style(Style::default().fg(Color::White))
+}
+
+/// Flattens a JSON response into table columns and rows, if it has a tabular shape.
+fn data_table(data: &Value) -> Option<(Vec<String>, Vec<Vec<String>>)> {
+    match data {
+        Value::Array(items) if items.iter().all(Value::is_object) => {
+            let mut headers: Vec<String> = Vec::new();
+            for item in items.iter().filter_map(Value::as_object) {
+                for key in item.keys() {
+                    if !headers.contains(key) {
+                        headers.push(key.clone());
+                    }
+                }
+            }
+            let rows = items.iter()
+                .map(|item| headers.iter().map(|h| cell_text(item.get(h))).collect())
+                .collect();
+            Some((headers, rows))
+        }
+        Value::Array(items) => {
+            Some((vec!["value".into()], items.iter().map(|v| vec![cell_text(Some(v))]).collect()))
+        }
+        Value::Object(map) => {
+            if let [(_, inner @ Value::Array(_))] = map.iter().collect::<Vec<_>>().as_slice() {
+                return data_table(inner);
+            }
+            let rows = map.iter().map(|(k, v)| vec![k.clone(), cell_text(Some(v))]).collect();
+            Some((vec!["field".into(), "value".into()], rows))
+        }
+        _ => None,
+    }
+}
+
+fn cell_text(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => "—".into(),
+        Some(Value::String(s)) => s.clone(),
+        Some(v) => v.to_string(),
+    }
+}
+
+fn draw_confirm(frame: &mut Frame, area: Rect, command: &str) {
+    let overlay = centered_rect(area, 56, 7);
+    frame.render_widget(Clear, overlay);
+    let lines = vec![
+        Line::from(""),
+        Line::from(Span::styled("  This action cannot be undone:", Style::default().fg(Color::Gray))),
+        Line::from(Span::styled(format!("  {command}"), Style::default().fg(Color::LightRed).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from(Span::styled("  [ y = run  |  n / Esc = cancel ]", Style::default().fg(Color::DarkGray))),
+    ];
+    let popup = Paragraph::new(Text::from(lines)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(ratatui::widgets::BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Red))
+            .title(Span::styled(" CONFIRM ", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))),
+    );
+    frame.render_widget(popup, overlay);
+}
+
+pub fn handle_admin_key(app: &mut App, key: crossterm::event::KeyEvent) {
+    use crossterm::event::KeyCode;
+
+    if app.admin.confirm {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => app.submit_admin(),
+            _ => app.admin.confirm = false,
+        }
+        return;
+    }
+
+    let arg_count = app.admin.command().args.len();
+    if let Some(field) = app.admin.field {
+        match key.code {
+            KeyCode::Esc => app.admin.field = None,
+            KeyCode::Enter => app.submit_admin(),
+            KeyCode::Tab | KeyCode::Down => app.admin.field = Some((field + 1) % arg_count),
+            KeyCode::BackTab | KeyCode::Up => app.admin.field = Some((field + arg_count - 1) % arg_count),
+            KeyCode::Backspace => { app.admin.fields[field].pop(); }
+            KeyCode::Char(c) => app.admin.fields[field].push(c),
+            _ => {}
+        }
+        return;
+    }
+
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => app.screen = Screen::Chat,
+        KeyCode::Up | KeyCode::Char('k') => app.admin.select(app.admin.cursor.saturating_sub(1)),
+        KeyCode::Down | KeyCode::Char('j') => app.admin.select(app.admin.cursor + 1),
+        KeyCode::Tab if arg_count > 0 => app.admin.field = Some(0),
+        KeyCode::Enter if arg_count > 0 && app.admin.fields.iter().all(String::is_empty) => {
+            app.admin.field = Some(0);
+        }
+        KeyCode::Enter => app.submit_admin(),
+        KeyCode::Char('[') => {
+            app.admin.viewing = (app.admin.viewing + 1).min(app.admin.results.len().saturating_sub(1));
+        }
+        KeyCode::Char(']') => app.admin.viewing = app.admin.viewing.saturating_sub(1),
+        _ => {}
+    }
+}
@@ -5,42 +5,82 @@ use ratatui::{
     Frame,
 };
 use crate::app::App;
+use crate::net::AuthFailure;
+use crate::keymap::Action;
 use crate::ui::centered_rect;
 
+fn failure_text(failure: &AuthFailure) -> (String, &'static str) {
+    match failure {
+        AuthFailure::NotEnrolled(reason) => (
+            format!("This key is not enrolled ({reason})"),
+            "Enter an invite code from the admin to enroll it.",
+        ),
+        AuthFailure::InvalidCode => (
+            "Invalid invite code".into(),
+            "Check it for typos, or paste the whole ttychat:// link.",
+        ),
+        AuthFailure::CodeExpired => (
+            "This invite code has expired".into(),
+            "Ask the admin for a new invite.",
+        ),
+        AuthFailure::UsernameTaken => (
+            "That username is already taken".into(),
+            "Pick another username and submit again.",
+        ),
+        AuthFailure::Rejected(reason) => (
+            format!("Enrollment refused: {reason}"),
+            "Ask the admin for help.",
+        ),
+    }
+}
+
 pub fn draw_enroll(frame: &mut Frame, app: &App) {
     let area = frame.area();
-    let center = centered_rect(area, 60, 12);
+    let center = centered_rect(area, 64, 15);
 
-    let status = app.status_msg.as_deref().unwrap_or("");
-    let code = &app.enroll_form.invite_code;
+    let form = &app.enroll_form;
     let cursor_visible = (app.tick_count / 6).wrapping_rem(2) == 0;
-    let field_display = format!(
-        "{}{}",
-        code,
-        if cursor_visible { "█" } else { " " }
-    );
+    let field = |label: &str, value: &str, index: usize| {
+        let active = form.focused == index;
+        let caret = if active && cursor_visible { "█" } else { " " };
+        Line::from(vec![
+            Span::styled(
+                format!("  {label:10}"),
+                Style::default().fg(if active { Color::Cyan } else { Color::DarkGray }),
+            ),
+            Span::styled(
+                format!("{value}{caret}"),
+                if active {
+                    Style::default().fg(Color::LightCyan).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::Gray)
+                },
+            ),
+        ])
+    };
+
+    let (title, hint) = match &form.failure {
+        Some(failure) => failure_text(failure),
+        None => (String::new(), "Paste an invite link or type the code from the admin."),
+    };
+    let status = app.status_msg.as_deref().unwrap_or("");
 
     let lines = vec![
-        Line::from(""),
-        Line::from(Span::styled(status, Style::default().fg(Color::Red))),
         Line::from(""),
         Line::from(Span::styled(
-            "  Enter your invite code from the admin:",
-            Style::default().fg(Color::Gray),
+            format!("  {title}"),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
         )),
+        Line::from(Span::styled(format!("  {hint}"), Style::default().fg(Color::Gray))),
         Line::from(""),
-        Line::from(Span::styled(
-            format!("  ▶ {field_display}"),
-            Style::default().fg(Color::LightCyan).add_modifier(Modifier::BOLD),
-        )),
+        Line::from(Span::styled(format!("  Server    {}", app.chat.server), Style::default().fg(Color::DarkGray))),
+        field("Invite", &form.invite_code, 0),
+        field("Username", &form.username, 1),
         Line::from(""),
-        Line::from(Span::styled(
-            "  Ask admin for invite",
-            Style::default().fg(Color::DarkGray),
-        )),
+        Line::from(Span::styled(format!("  {status}"), Style::default().fg(Color::Yellow))),
         Line::from(""),
         Line::from(Span::styled(
-            "  [ Enter = submit  |  Esc = back ]",
+            "  [ Enter = submit  |  Tab = next field  |  Esc = back ]",
             Style::default().fg(Color::DarkGray),
         )),
     ];
@@ -55,19 +95,24 @@ pub fn draw_enroll(frame: &mut Frame, app: &App) {
     frame.render_widget(para, center);
 }
 
-pub fn handle_enroll_key(app: &mut App, key: crossterm::event::KeyEvent) {
+pub fn handle_enroll_key(app: &mut App, key: crossterm::event::KeyEvent, actions: &[Action]) {
     use crossterm::event::KeyCode;
+    let form = &mut app.enroll_form;
+    if actions.contains(&Action::NextField) || actions.contains(&Action::PrevField) {
+        form.focused = 1 - form.focused;
+        return;
+    }
+    let field = if form.focused == 0 { &mut form.invite_code } else { &mut form.username };
     match key.code {
-        KeyCode::Esc => { app.screen = crate::app::Screen::Connect; }
-        KeyCode::Enter => {
-            if !app.enroll_form.invite_code.trim().is_empty() {
-                let code = app.enroll_form.invite_code.trim().to_string();
-                app.enroll_form.invite_code.clear();
-                app.start_connection(Some(code));
-            }
+        KeyCode::Esc => {
+            app.net_cmd_tx = None;
+            app.status_msg = None;
+            app.screen = crate::app::Screen::Connect;
         }
-        KeyCode::Backspace => { app.enroll_form.invite_code.pop(); }
-        KeyCode::Char(c) => { app.enroll_form.invite_code.push(c); }
+        KeyCode::Enter => app.submit_enroll(),
+        KeyCode::Up | KeyCode::Down => form.focused = 1 - form.focused,
+        KeyCode::Backspace => { field.pop(); }
+        KeyCode::Char(c) => field.push(c),
         _ => {}
     }
 }
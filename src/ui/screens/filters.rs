@@ -0,0 +1,171 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+use crate::app::{App, Screen};
+use crate::config::{ContentFilter, FilterAction};
+use crate::ui::centered_rect;
+
+pub fn draw_filters(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+    let form = &app.filters_form;
+    let filters = &app.config.filters;
+    let height = (filters.len().max(1) as u16 + 14).min(area.height);
+    let center = centered_rect(area, 70, height);
+
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            "  Messages matching a pattern are hidden or dimmed.",
+            Style::default().fg(Color::Gray),
+        )),
+        Line::from(""),
+    ];
+
+    if filters.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "  (no filters)",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    for (i, f) in filters.iter().enumerate() {
+        let selected = i == form.cursor && form.input.is_none();
+        let marker = if selected { "▶ " } else { "  " };
+        let style = if selected {
+            Style::default().fg(Color::LightCyan).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        let valid = regex::Regex::new(&f.pattern).is_ok();
+        lines.push(Line::from(vec![
+            Span::raw("  "),
+            Span::styled(marker, style),
+            Span::styled(format!("[{:4}] ", f.action.as_str()), Style::default().fg(Color::Yellow)),
+            Span::styled(f.pattern.clone(), style),
+            Span::styled(
+                if valid { "" } else { "  (invalid)" },
+                Style::default().fg(Color::Red),
+            ),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+    if let Some(input) = &form.input {
+        let cursor_visible = (app.tick_count / 6).wrapping_rem(2) == 0;
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("  New [{}] ", form.action.as_str()),
+                Style::default().fg(Color::Yellow),
+            ),
+            Span::styled(
+                format!("{input}{}", if cursor_visible { "█" } else { " " }),
+                Style::default().fg(Color::LightCyan).add_modifier(Modifier::BOLD),
+            ),
+        ]));
+    } else {
+        lines.push(Line::from(""));
+    }
+    lines.push(Line::from(Span::styled(
+        form.error.clone().unwrap_or_default(),
+        Style::default().fg(Color::Red),
+    )));
+
+    let on_off = if app.config.hidden_placeholder { "shown" } else { "dropped" };
+    lines.push(Line::from(Span::styled(
+        format!("  Hidden messages: {on_off} · ignored users: {}", app.config.ignored.len()),
+        Style::default().fg(Color::Gray),
+    )));
+    lines.push(Line::from(""));
+    let hint = if form.input.is_some() {
+        "  [ Enter = add  |  Tab = hide/dim  |  Esc = cancel ]"
+    } else {
+        "  [ a = add  |  d = delete  |  t = hide/dim  |  p = placeholder  |  Esc = back ]"
+    };
+    lines.push(Line::from(Span::styled(hint, Style::default().fg(Color::DarkGray))));
+
+    let para = Paragraph::new(Text::from(lines)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(ratatui::widgets::BorderType::Rounded)
+            .border_style(Style::default().fg(Color::DarkGray))
+            .title(Span::styled(" FILTERS ", Style::default().fg(Color::Cyan))),
+    );
+    frame.render_widget(para, center);
+}
+
+pub fn handle_filters_key(app: &mut App, key: crossterm::event::KeyEvent) {
+    use crossterm::event::KeyCode;
+
+    if let Some(input) = app.filters_form.input.as_mut() {
+        match key.code {
+            KeyCode::Esc => {
+                app.filters_form.input = None;
+                app.filters_form.error = None;
+            }
+            KeyCode::Tab => {
+                app.filters_form.action = match app.filters_form.action {
+                    FilterAction::Hide => FilterAction::Dim,
+                    FilterAction::Dim => FilterAction::Hide,
+                };
+            }
+            KeyCode::Backspace => { input.pop(); }
+            KeyCode::Char(c) => { input.push(c); }
+            KeyCode::Enter if !input.is_empty() => {
+                let pattern = input.clone();
+                if let Err(e) = regex::Regex::new(&pattern) {
+                    let first = e.to_string().lines().last().unwrap_or_default().trim().to_string();
+                    app.filters_form.error = Some(format!("  Invalid pattern: {first}"));
+                    return;
+                }
+                let action = app.filters_form.action;
+                app.config.filters.push(ContentFilter { pattern, action });
+                app.filters_form.cursor = app.config.filters.len() - 1;
+                app.filters_form.input = None;
+                app.filters_form.error = None;
+                save_filters(app);
+            }
+            _ => {}
+        }
+        return;
+    }
+
+    let count = app.config.filters.len();
+    let cursor = app.filters_form.cursor.min(count.saturating_sub(1));
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => app.screen = Screen::Chat,
+        KeyCode::Up | KeyCode::Char('k') => app.filters_form.cursor = cursor.saturating_sub(1),
+        KeyCode::Down | KeyCode::Char('j') => {
+            app.filters_form.cursor = (cursor + 1).min(count.saturating_sub(1));
+        }
+        KeyCode::Char('a') => {
+            app.filters_form.input = Some(String::new());
+            app.filters_form.action = FilterAction::Hide;
+        }
+        KeyCode::Char('d') | KeyCode::Delete if count > 0 => {
+            app.config.filters.remove(cursor);
+            app.filters_form.cursor = cursor.min(count.saturating_sub(2));
+            save_filters(app);
+        }
+        KeyCode::Char('t') if count > 0 => {
+            let f = &mut app.config.filters[cursor];
+            f.action = match f.action {
+                FilterAction::Hide => FilterAction::Dim,
+                FilterAction::Dim => FilterAction::Hide,
+            };
+            save_filters(app);
+        }
+        KeyCode::Char('p') => {
+            app.config.hidden_placeholder = !app.config.hidden_placeholder;
+            let _ = app.config.save();
+        }
+        _ => {}
+    }
+}
+
+fn save_filters(app: &mut App) {
+    app.filters = App::compile_filters(&app.config);
+    app.refilter_messages();
+    let _ = app.config.save();
+}
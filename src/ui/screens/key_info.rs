@@ -9,10 +9,14 @@ use crate::ui::centered_rect;
 
 pub fn draw_key_info(frame: &mut Frame, app: &App) {
     let area = frame.area();
-    let center = centered_rect(area, 70, 14);
     let pubkey = app.pubkey_b64.as_deref().unwrap_or("(none)");
+    let fingerprint = crate::crypto::fingerprint(pubkey).unwrap_or_default();
+    let symbols = crate::crypto::fingerprint_symbols(pubkey).unwrap_or_default();
+    let emoji: Vec<&str> = symbols.iter().map(|(e, _)| *e).collect();
+    let words: Vec<&str> = symbols.iter().map(|(_, w)| *w).collect();
+    let art = crate::crypto::randomart(pubkey).unwrap_or_default();
 
-    let lines = vec![
+    let mut lines = vec![
         Line::from(""),
         Line::from(Span::styled(
             "  NEW IDENTITY GENERATED",
@@ -23,11 +27,22 @@ pub fn draw_key_info(frame: &mut Frame, app: &App) {
             "  Your public key (send this to the admin):",
             Style::default().fg(Color::Gray),
         )),
-        Line::from(""),
         Line::from(Span::styled(
             format!("  {pubkey}"),
             Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
         )),
+        Line::from(""),
+        Line::from(Span::styled(
+            "  Fingerprint (compare this when the admin reads it back):",
+            Style::default().fg(Color::Gray),
+        )),
+        Line::from(Span::styled(format!("  {fingerprint}"), Style::default().fg(Color::White))),
+        Line::from(Span::styled(format!("  {}", emoji.join(" ")), Style::default().fg(Color::White))),
+        Line::from(Span::styled(format!("  {}", words.join(", ")), Style::default().fg(Color::DarkGray))),
+        Line::from(""),
+    ];
+    lines.extend(art.into_iter().map(|l| Line::from(Span::styled(format!("  {l}"), Style::default().fg(Color::Cyan)))));
+    lines.extend([
         Line::from(""),
         Line::from(Span::styled(
             "  Ask an admin in the chat for invite:",
@@ -42,7 +57,8 @@ pub fn draw_key_info(frame: &mut Frame, app: &App) {
             "  [ Press Enter or Esc to continue ]",
             Style::default().fg(Color::DarkGray),
         )),
-    ];
+    ]);
+    let center = centered_rect(area, 72, lines.len() as u16 + 2);
 
     let para = Paragraph::new(Text::from(lines)).block(
         Block::default()
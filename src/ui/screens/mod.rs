@@ -1,9 +1,13 @@
+pub mod admin;
 pub mod auth;
 pub mod enroll;
 pub mod error;
+pub mod filters;
 pub mod key_info;
 
+pub use admin::{draw_admin, handle_admin_key};
 pub use auth::draw_auth;
 pub use enroll::{draw_enroll, handle_enroll_key};
 pub use error::draw_error;
+pub use filters::{draw_filters, handle_filters_key};
 pub use key_info::draw_key_info;
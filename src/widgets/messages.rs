@@ -1,9 +1,75 @@
+use chrono::{Local, NaiveDate};
 use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span},
 };
+use std::collections::HashMap;
 
-use crate::app::ChatMessage;
+use crate::app::{ChatMessage, ChatState};
+use crate::config::TimestampFormat;
+
+pub struct MessageLayout<'a> {
+    pub lines: Vec<Line<'a>>,
+    /// Row of each message's first line, indexed like `ChatState::messages`.
+    pub starts: Vec<usize>,
+}
+
+pub fn layout_messages<'a>(
+    chat: &'a ChatState,
+    ts_fmt: &TimestampFormat,
+    max_width: usize,
+) -> MessageLayout<'a> {
+    let mut lines = Vec::new();
+    let mut starts = Vec::with_capacity(chat.messages.len());
+    let ids: HashMap<&str, usize> = chat.messages.iter().enumerate()
+        .filter_map(|(i, m)| m.id.as_deref().map(|id| (id, i)))
+        .collect();
+    let mut last_day = None;
+    for (i, msg) in chat.messages.iter().enumerate() {
+        let day = msg.timestamp.date_naive();
+        if last_day != Some(day) {
+            last_day = Some(day);
+            lines.push(divider_line(&day_label(day), max_width, Color::DarkGray));
+        }
+        if chat.unread_marker == Some(i) {
+            lines.push(divider_line("new", max_width, Color::LightRed));
+        }
+        starts.push(lines.len());
+        let parent = msg.reply_to.as_deref()
+            .and_then(|id| ids.get(id))
+            .map(|&p| &chat.messages[p]);
+        let rendered = render_message(msg, parent, &chat.username, ts_fmt, max_width);
+        if chat.selected == Some(i) {
+            let highlight = Style::default().bg(Color::Rgb(40, 44, 60));
+            lines.extend(rendered.into_iter().map(|l| l.patch_style(highlight)));
+        } else {
+            lines.extend(rendered);
+        }
+    }
+    MessageLayout { lines, starts }
+}
+
+fn day_label(day: NaiveDate) -> String {
+    let today = Local::now().date_naive();
+    if day == today {
+        "Today".into()
+    } else if today.pred_opt() == Some(day) {
+        "Yesterday".into()
+    } else {
+        day.format("%a, %d %b %Y").to_string()
+    }
+}
+
+pub fn divider_line(label: &str, max_width: usize, color: Color) -> Line<'static> {
+    let label = format!(" {label} ");
+    let rest = max_width.saturating_sub(label.chars().count());
+    let left = "─".repeat(rest / 2);
+    let right = "─".repeat(rest - rest / 2);
+    Line::from(Span::styled(
+        format!("{left}{label}{right}"),
+        Style::default().fg(color).add_modifier(Modifier::BOLD),
+    ))
+}
 
 pub fn user_color_simple(username: &str) -> Color {
     use std::collections::hash_map::DefaultHasher;
@@ -17,11 +83,24 @@ pub fn user_color_simple(username: &str) -> Color {
     ];
     colors[(hash as usize) % colors.len()]
 }
-pub fn render_message<'a>(msg: &'a ChatMessage, my_username: &str, max_width: usize) -> Vec<Line<'a>> {
+pub fn render_message<'a>(
+    msg: &'a ChatMessage,
+    parent: Option<&ChatMessage>,
+    my_username: &str,
+    ts_fmt: &TimestampFormat,
+    max_width: usize,
+) -> Vec<Line<'a>> {
     let is_self = msg.from == my_username;
     let is_system = msg.is_system;
     let is_admin = msg.is_admin;
 
+    if msg.hidden {
+        return vec![Line::from(Span::styled(
+            format!("  ⋯ hidden message from {}", msg.from),
+            Style::default().fg(Color::Rgb(70, 70, 70)).add_modifier(Modifier::ITALIC),
+        ))];
+    }
+
     let user_color = if is_self {
         Color::LightCyan
     } else if is_system {
@@ -32,16 +111,18 @@ pub fn render_message<'a>(msg: &'a ChatMessage, my_username: &str, max_width: us
         user_color_simple(&msg.from)
     };
 
-    let time_str = format!("{} ", msg.timestamp);
+    let time_str = format!("{} ", ts_fmt.format(msg.timestamp, Local::now()));
     let time_len = time_str.len();
     let time_span = Span::styled(time_str, Style::default().fg(Color::DarkGray));
 
     let user_str = if is_system {
         format!("── {} ── ", msg.from)
+    } else if msg.is_direct {
+        format!("✉ {}: ", msg.from)
     } else {
         format!("{}: ", msg.from)
     };
-    let user_len = user_str.len();
+    let user_len = user_str.chars().count();
     let user_span = if is_system {
         Span::styled(
             user_str,
@@ -68,6 +149,13 @@ pub fn render_message<'a>(msg: &'a ChatMessage, my_username: &str, max_width: us
         Color::White
     };
 
+    let text_style = if msg.deleted {
+        Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC)
+    } else {
+        Style::default().fg(text_color)
+    };
+    let body = if msg.deleted { "message deleted" } else { msg.text.as_str() };
+
     let prefix_str = if is_self { "▶ " } else { "  " };
     let prefix_len = prefix_str.len();
     let prefix_span = Span::styled(prefix_str, Style::default().fg(Color::DarkGray));
@@ -80,24 +168,28 @@ pub fn render_message<'a>(msg: &'a ChatMessage, my_username: &str, max_width: us
     }
 
     let mut lines = Vec::new();
+    if msg.reply_to.is_some() {
+        lines.push(reply_header(parent, prefix_len, max_width));
+    }
+    let body_start = lines.len();
     let mut current_line_text = String::new();
     let mut current_width = 0;
 
-    for ch in msg.text.chars() {
+    for ch in body.chars() {
         let ch_width = unicode_width::UnicodeWidthChar::width(ch).unwrap_or(0);
         
         if current_width + ch_width > available_width {
-            let line_spans = if lines.is_empty() {
+            let line_spans = if lines.len() == body_start {
                 vec![
                     prefix_span.clone(),
                     time_span.clone(),
                     user_span.clone(),
-                    Span::styled(current_line_text.clone(), Style::default().fg(text_color)),
+                    Span::styled(current_line_text.clone(), text_style),
                 ]
             } else {
                 vec![
                     Span::raw(" ".repeat(header_width)),
-                    Span::styled(current_line_text.clone(), Style::default().fg(text_color)),
+                    Span::styled(current_line_text.clone(), text_style),
                 ]
             };
             lines.push(Line::from(line_spans));
@@ -109,22 +201,83 @@ pub fn render_message<'a>(msg: &'a ChatMessage, my_username: &str, max_width: us
         current_width += ch_width;
     }
 
-    if !current_line_text.is_empty() || lines.is_empty() {
-        let line_spans = if lines.is_empty() {
+    let last_width = current_width;
+    if !current_line_text.is_empty() || lines.len() == body_start {
+        let line_spans = if lines.len() == body_start {
             vec![
                 prefix_span,
                 time_span,
                 user_span,
-                Span::styled(current_line_text, Style::default().fg(text_color)),
+                Span::styled(current_line_text, text_style),
             ]
         } else {
             vec![
                 Span::raw(" ".repeat(header_width)),
-                Span::styled(current_line_text, Style::default().fg(text_color)),
+                Span::styled(current_line_text, text_style),
             ]
         };
         lines.push(Line::from(line_spans));
     }
 
+    if msg.edited && !msg.deleted {
+        const EDITED: &str = " (edited)";
+        let marker = Span::styled(EDITED, Style::default().fg(Color::DarkGray));
+        match lines.last_mut() {
+            Some(last) if last_width + EDITED.len() <= available_width => last.spans.push(marker),
+            _ => lines.push(Line::from(vec![Span::raw(" ".repeat(header_width)), marker])),
+        }
+    }
+
+    if !msg.reactions.is_empty() && !msg.deleted {
+        lines.push(reaction_line(msg, my_username, header_width));
+    }
+
+    if msg.dimmed {
+        for line in &mut lines {
+            line.style = line.style.add_modifier(Modifier::DIM);
+        }
+    }
+
     lines
 }
+
+fn reply_header(parent: Option<&ChatMessage>, indent: usize, max_width: usize) -> Line<'static> {
+    let text = match parent {
+        Some(p) => format!("↳ replying to {}: {}", p.from, p.visible_text()),
+        None => "↳ replying to an earlier message".to_string(),
+    };
+    let budget = max_width.saturating_sub(indent + 1);
+    let mut shown = String::new();
+    let mut width = 0;
+    for ch in text.chars().filter(|c| !c.is_control()) {
+        let w = unicode_width::UnicodeWidthChar::width(ch).unwrap_or(0);
+        if width + w + 1 > budget {
+            shown.push('…');
+            break;
+        }
+        shown.push(ch);
+        width += w;
+    }
+    Line::from(vec![
+        Span::raw(" ".repeat(indent)),
+        Span::styled(
+            shown,
+            Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+        ),
+    ])
+}
+
+fn reaction_line(msg: &ChatMessage, my_username: &str, indent: usize) -> Line<'static> {
+    let mut spans = vec![Span::raw(" ".repeat(indent))];
+    for (emoji, users) in &msg.reactions {
+        let mine = users.iter().any(|u| u == my_username);
+        let style = if mine {
+            Style::default().fg(Color::Black).bg(Color::Cyan)
+        } else {
+            Style::default().fg(Color::Gray).bg(Color::Rgb(40, 40, 40))
+        };
+        spans.push(Span::styled(format!(" {emoji} {} ", users.len()), style));
+        spans.push(Span::raw(" "));
+    }
+    Line::from(spans)
+}